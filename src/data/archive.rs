@@ -0,0 +1,97 @@
+//! Tar archives of records.
+
+use crate::data::record::Record;
+
+use std::io;
+
+use tar::{Builder, EntryType, Header};
+
+pub const MIME: &str = "application/x-tar";
+
+/// Packs the files of a bundle, the file of a binary record,
+/// or the content of a text record as `<key>.txt`.
+pub fn to_tar(key: &str, record: &Record) -> io::Result<Vec<u8>> {
+    let mut builder = Builder::new(Vec::new());
+    let mut append = |name: &str, content: &[u8]| {
+        let mut header = Header::new_ustar();
+        header.set_entry_type(EntryType::Regular);
+        header.set_mode(0o644);
+        header.set_mtime(record.editing_time);
+        header.set_size(content.len() as u64);
+        builder.append_data(&mut header, name, content)
+    };
+    if record.is_bundle() {
+        for f in &record.files {
+            append(&f.name, f.content.as_bytes())?;
+        }
+    } else if let Some(ref binary) = record.binary {
+        let bytes = base64::decode(&record.content)
+            .map_err(|err| io::Error::new(io::ErrorKind::InvalidData, err))?;
+        append(&binary.file_name, &bytes)?;
+    } else {
+        append(&format!("{}.txt", key), record.content.as_bytes())?;
+    }
+    builder.into_inner()
+}
+
+#[cfg(test)]
+#[test]
+fn test_to_tar() {
+    use crate::data::record::{Binary, RecordFile};
+    use std::io::Read;
+
+    let entries = |tar: Vec<u8>| -> Vec<(String, String)> {
+        let mut archive = tar::Archive::new(tar.as_slice());
+        archive
+            .entries()
+            .unwrap()
+            .map(|e| {
+                let mut e = e.unwrap();
+                let name = e.path().unwrap().to_str().unwrap().to_owned();
+                let mut content = String::new();
+                e.read_to_string(&mut content).unwrap();
+                (name, content)
+            })
+            .collect()
+    };
+
+    let mut record = Record::sample();
+    assert_eq!(
+        entries(to_tar("KEY", &record).unwrap()),
+        vec![("KEY.txt".into(), "// content".into())]
+    );
+
+    record.binary = Some(Binary {
+        file_name: "a.bin".into(),
+        content_type: "application/octet-stream".into(),
+        size: 3,
+    });
+    record.content = base64::encode(b"a\0b");
+    assert_eq!(
+        entries(to_tar("KEY", &record).unwrap()),
+        vec![("a.bin".into(), "a\0b".into())]
+    );
+    record.binary = None;
+
+    let long_name = "n".repeat(120);
+    record.content.clear();
+    record.files = vec![
+        RecordFile {
+            name: "a.rs".into(),
+            lang: "rust".into(),
+            content: "fn main() {}".into(),
+        },
+        RecordFile {
+            name: long_name.clone(),
+            lang: "plaintext".into(),
+            content: String::new(),
+        },
+    ];
+    assert_eq!(
+        entries(to_tar("KEY", &record).unwrap()),
+        vec![
+            ("a.rs".into(), "fn main() {}".into()),
+            (long_name, String::new())
+        ]
+    );
+}
@@ -0,0 +1,94 @@
+//! Binary records, uploaded as files.
+//!
+//! The bytes are kept as base64 in `Record::content`, so binary records pass
+//! through sealing, compression and every backend like text records.
+
+use crate::data::record::RecordFile;
+
+pub const OCTET_STREAM: &str = "application/octet-stream";
+const DEFAULT_FILE_NAME: &str = "file";
+
+/// Keeps the last path segment of a client file name, as a valid `RecordFile` name.
+pub fn sanitize_file_name(name: &str) -> String {
+    let base = name.rsplit(&['/', '\\'][..]).next().unwrap_or("");
+    let mut out = String::new();
+    for c in base.chars().filter(|&c| !c.is_control() && c != '"') {
+        if out.len() + c.len_utf8() > 128 {
+            break;
+        }
+        out.push(c);
+    }
+    if RecordFile::is_valid_name(&out) {
+        out
+    } else {
+        DEFAULT_FILE_NAME.into()
+    }
+}
+
+fn is_token(s: &str) -> bool {
+    !s.is_empty()
+        && s.bytes()
+            .all(|b| b.is_ascii_alphanumeric() || b"!#$&^_.+-".contains(&b))
+}
+
+/// Keeps the essence (`type/subtype`) of a valid MIME type, or falls back to `OCTET_STREAM`.
+pub fn sanitize_content_type(content_type: Option<&str>) -> String {
+    content_type
+        .and_then(|ct| {
+            let essence = ct.split(';').next()?.trim().to_ascii_lowercase();
+            let (ty, subtype) = essence.split_once('/')?;
+            if is_token(ty) && is_token(subtype) {
+                Some(essence)
+            } else {
+                None
+            }
+        })
+        .unwrap_or_else(|| OCTET_STREAM.into())
+}
+
+/// An attachment header with an ASCII fallback and the UTF-8 name (RFC 6266).
+pub fn content_disposition(file_name: &str) -> String {
+    let ascii: String = file_name
+        .chars()
+        .map(|c| match c {
+            ' '..='~' if c != '"' && c != '\\' => c,
+            _ => '_',
+        })
+        .collect();
+    let mut encoded = String::new();
+    for b in file_name.bytes() {
+        if b.is_ascii_alphanumeric() || b"-._~".contains(&b) {
+            encoded.push(b as char);
+        } else {
+            encoded.push_str(&format!("%{:02X}", b));
+        }
+    }
+    format!(
+        "attachment; filename=\"{}\"; filename*=UTF-8''{}",
+        ascii, encoded
+    )
+}
+
+#[cfg(test)]
+#[test]
+fn test_binary() {
+    assert_eq!(sanitize_file_name("C:\\Users\\me\\shot.png"), "shot.png");
+    assert_eq!(sanitize_file_name("../a\"b\n.txt"), "ab.txt");
+    assert_eq!(sanitize_file_name(".."), DEFAULT_FILE_NAME);
+    assert_eq!(sanitize_file_name("dir/"), DEFAULT_FILE_NAME);
+    assert_eq!(sanitize_file_name(&"é".repeat(100)).len(), 128);
+
+    assert_eq!(sanitize_content_type(Some("Image/PNG")), "image/png");
+    assert_eq!(
+        sanitize_content_type(Some("text/plain; charset=utf-8")),
+        "text/plain"
+    );
+    assert_eq!(sanitize_content_type(Some("text/<b>")), OCTET_STREAM);
+    assert_eq!(sanitize_content_type(Some("nonsense")), OCTET_STREAM);
+    assert_eq!(sanitize_content_type(None), OCTET_STREAM);
+
+    assert_eq!(
+        content_disposition("naïve shot.png"),
+        "attachment; filename=\"na_ve shot.png\"; filename*=UTF-8''na%C3%AFve%20shot.png"
+    );
+}
@@ -0,0 +1,230 @@
+//! Compression of record content at rest.
+//!
+//! The content is moved into its own body, compressed alone if that saves space,
+//! so that equal contents are stored alike whatever the rest of the record and
+//! can be shared. Files and all revisions are packed into one zstd frame, which
+//! is kept only if it is smaller than the text it replaces.
+
+use crate::data::record::{Body, Record, RecordFile, Revision};
+
+use std::io;
+use std::mem;
+
+use serde::{Deserialize, Serialize};
+
+#[derive(Serialize, Deserialize)]
+struct Bulk {
+    #[serde(default, skip_serializing_if = "String::is_empty")]
+    content: String, // packed here before contents had their own body
+    #[serde(default)]
+    files: Vec<RecordFile>,
+    revisions: Vec<Revision>,
+}
+
+fn files_len(files: &[RecordFile]) -> usize {
+    files
+        .iter()
+        .map(|f| f.name.len() + f.lang.len() + f.content.len())
+        .sum()
+}
+
+impl Bulk {
+    fn text_len(&self) -> usize {
+        self.content.len()
+            + files_len(&self.files)
+            + self
+                .revisions
+                .iter()
+                .map(|r| r.title.len() + r.lang.len() + r.content.len() + files_len(&r.files))
+                .sum::<usize>()
+    }
+}
+
+#[derive(Debug, Clone, Copy)]
+pub struct Compressor {
+    level: i32,
+}
+
+impl Compressor {
+    pub fn new(level: i32) -> Self {
+        Self { level }
+    }
+
+    /// Moves the content into its body, and packs files and revisions if that saves space.
+    /// The record must not be packed yet.
+    pub fn compress(&self, record: &mut Record) {
+        if !record.content.is_empty() {
+            let content = mem::take(&mut record.content);
+            record.body = Some(match zstd::encode_all(content.as_bytes(), self.level) {
+                Ok(zstd) if zstd.len() < content.len() => Body::Zstd(zstd.into()),
+                _ => Body::Text(content.into()),
+            });
+        }
+        if record.files.is_empty() && record.revisions.is_empty() {
+            return;
+        }
+        let bulk = Bulk {
+            content: String::new(),
+            files: mem::take(&mut record.files),
+            revisions: mem::take(&mut record.revisions),
+        };
+        // assert: Bulk is valid
+        let json = serde_json::to_vec(&bulk).unwrap();
+        match zstd::encode_all(json.as_slice(), self.level) {
+            Ok(packed) if packed.len() < bulk.text_len() => record.packed = Some(packed.into()),
+            _ => {
+                record.files = bulk.files;
+                record.revisions = bulk.revisions;
+            }
+        }
+    }
+}
+
+/// Moves the content into its body as is, for sharing without a compressor.
+pub fn store_body(record: &mut Record) {
+    if !record.content.is_empty() {
+        record.body = Some(Body::Text(mem::take(&mut record.content).into()));
+    }
+}
+
+/// Unpacks a record packed by `Compressor::compress` or `store_body`.
+/// Does nothing to an unpacked record.
+pub fn decompress(record: &mut Record) -> io::Result<()> {
+    match record.body.take() {
+        None => {}
+        Some(Body::Text(text)) => record.content = String::from(&*text),
+        Some(Body::Zstd(zstd)) => {
+            let bytes = zstd::decode_all(&zstd[..])?;
+            record.content = String::from_utf8(bytes)
+                .map_err(|err| io::Error::new(io::ErrorKind::InvalidData, err))?;
+        }
+    }
+    let packed = match record.packed {
+        None => return Ok(()),
+        Some(ref packed) => packed,
+    };
+    let json = zstd::decode_all(&packed[..])?;
+    let bulk: Bulk = serde_json::from_slice(&json)?;
+    if !bulk.content.is_empty() {
+        record.content = bulk.content;
+    }
+    record.files = bulk.files;
+    record.revisions = bulk.revisions;
+    record.packed = None;
+    Ok(())
+}
+
+/// Serializes `Option<Arc<[u8]>>` as a base64 string.
+pub mod base64_bytes {
+    use std::sync::Arc;
+
+    use serde::{Deserialize, Deserializer, Serializer};
+
+    pub fn serialize<S: Serializer>(bytes: &Option<Arc<[u8]>>, s: S) -> Result<S::Ok, S::Error> {
+        match bytes {
+            Some(bytes) => s.serialize_str(&base64::encode(bytes)),
+            None => s.serialize_none(),
+        }
+    }
+
+    pub fn deserialize<'de, D: Deserializer<'de>>(d: D) -> Result<Option<Arc<[u8]>>, D::Error> {
+        match Option::<String>::deserialize(d)? {
+            Some(s) => base64::decode(&s)
+                .map(|bytes| Some(bytes.into()))
+                .map_err(serde::de::Error::custom),
+            None => Ok(None),
+        }
+    }
+
+    /// The same for `Arc<[u8]>`.
+    pub mod plain {
+        use std::sync::Arc;
+
+        use serde::{Deserialize, Deserializer, Serializer};
+
+        pub fn serialize<S: Serializer>(bytes: &Arc<[u8]>, s: S) -> Result<S::Ok, S::Error> {
+            s.serialize_str(&base64::encode(bytes))
+        }
+
+        pub fn deserialize<'de, D: Deserializer<'de>>(d: D) -> Result<Arc<[u8]>, D::Error> {
+            let s = String::deserialize(d)?;
+            base64::decode(&s)
+                .map(Arc::from)
+                .map_err(serde::de::Error::custom)
+        }
+    }
+}
+
+#[cfg(test)]
+#[test]
+fn test_compress() {
+    let compressor = Compressor::new(3);
+
+    // not worth it
+    let mut record = Record::sample();
+    compressor.compress(&mut record);
+    assert!(record.packed.is_none());
+    assert!(record.content.is_empty());
+    assert!(matches!(record.body, Some(Body::Text(ref text)) if &**text == "// content"));
+    decompress(&mut record).unwrap();
+    assert_eq!(record.content, "// content");
+    assert!(record.body.is_none());
+
+    let content = "INFO request handled\n".repeat(100);
+    record.content = content.clone();
+    let file = RecordFile {
+        name: "log.txt".into(),
+        lang: "plaintext".into(),
+        content: content.clone(),
+    };
+    record.edit("t".into(), "text".into(), content.repeat(2), vec![file], 2);
+    let mut copy = Record::sample();
+    copy.content = content.repeat(2);
+    compressor.compress(&mut record);
+    compressor.compress(&mut copy);
+    let packed_len = record.packed.as_ref().unwrap().len();
+    assert!(packed_len * 10 < content.len());
+    assert!(record.content.is_empty() && record.revisions.is_empty() && record.files.is_empty());
+
+    // the body depends on the content only
+    let body = record.body.as_ref().unwrap();
+    assert!(matches!(body, Body::Zstd(_)));
+    assert!(body.as_ref().len() * 10 < content.len());
+    assert_eq!(body.as_ref(), copy.body.as_ref().unwrap().as_ref());
+    assert!(copy.packed.is_none());
+
+    let json = serde_json::to_string(&record).unwrap();
+    let mut record: Record = serde_json::from_str(&json).unwrap();
+    assert_eq!(record.packed.as_ref().unwrap().len(), packed_len);
+
+    decompress(&mut record).unwrap();
+    assert_eq!(record.content, content.repeat(2));
+    assert_eq!(record.revisions[0].content, content);
+    assert_eq!(record.files[0].content, content);
+    assert!(record.packed.is_none() && record.body.is_none());
+    decompress(&mut record).unwrap();
+
+    // without a compressor
+    let mut plain = Record::sample();
+    plain.content = content.repeat(2);
+    store_body(&mut plain);
+    assert!(matches!(plain.body, Some(Body::Text(_))) && plain.content.is_empty());
+    decompress(&mut plain).unwrap();
+    assert_eq!(plain.content, content.repeat(2));
+
+    // packed before contents had their own body
+    let bulk = Bulk {
+        content: "old".into(),
+        files: Vec::new(),
+        revisions: Vec::new(),
+    };
+    let json = serde_json::to_vec(&bulk).unwrap();
+    record.packed = Some(zstd::encode_all(json.as_slice(), 3).unwrap().into());
+    decompress(&mut record).unwrap();
+    assert_eq!(record.content, "old");
+
+    record.packed = Some(vec![1, 2, 3].into());
+    assert!(decompress(&mut record).is_err());
+    record.body = Some(Body::Zstd(vec![1, 2, 3].into()));
+    assert!(decompress(&mut record).is_err());
+}
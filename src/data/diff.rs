@@ -0,0 +1,261 @@
+//! Line diffs between records, as hunks, unified text or a side-by-side page.
+
+use crate::data::highlight::escape;
+
+use std::fmt::Write;
+use std::time::Duration;
+
+use serde::Serialize;
+use similar::{ChangeTag, TextDiff};
+
+pub const DEFAULT_CONTEXT: usize = 3;
+pub const MAX_CONTEXT: usize = 100;
+
+// past this, the diff is still correct but may not be minimal
+const TIMEOUT: Duration = Duration::from_millis(500);
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Tag {
+    Equal,
+    Delete,
+    Insert,
+}
+
+impl Tag {
+    fn prefix(self) -> char {
+        match self {
+            Tag::Equal => ' ',
+            Tag::Delete => '-',
+            Tag::Insert => '+',
+        }
+    }
+}
+
+/// A line without its line break.
+#[derive(Debug, Serialize)]
+pub struct DiffLine {
+    pub tag: Tag,
+    pub content: String,
+}
+
+/// Line numbers start from 1, or are the line before for an empty range, as in `diff -u`.
+#[derive(Debug, Serialize)]
+pub struct Hunk {
+    pub old_start: usize,
+    pub old_lines: usize,
+    pub new_start: usize,
+    pub new_lines: usize,
+    pub lines: Vec<DiffLine>,
+}
+
+impl Hunk {
+    fn header(&self) -> String {
+        format!(
+            "@@ -{},{} +{},{} @@",
+            self.old_start, self.old_lines, self.new_start, self.new_lines
+        )
+    }
+}
+
+fn unified_start(start: usize, len: usize) -> usize {
+    if len == 0 {
+        start
+    } else {
+        start + 1
+    }
+}
+
+fn strip_line_break(line: &str) -> &str {
+    let line = line.strip_suffix('\n').unwrap_or(line);
+    line.strip_suffix('\r').unwrap_or(line)
+}
+
+/// Diffs `old` and `new` line by line, with `context` unchanged lines around changes.
+pub fn hunks(old: &str, new: &str, context: usize) -> Vec<Hunk> {
+    let diff = TextDiff::configure().timeout(TIMEOUT).diff_lines(old, new);
+    diff.grouped_ops(context)
+        .iter()
+        .map(|group| {
+            // assert: groups are not empty
+            let (first, last) = (&group[0], &group[group.len() - 1]);
+            let old_range = first.old_range().start..last.old_range().end;
+            let new_range = first.new_range().start..last.new_range().end;
+            let lines = group
+                .iter()
+                .flat_map(|op| diff.iter_changes(op))
+                .map(|change| DiffLine {
+                    tag: match change.tag() {
+                        ChangeTag::Equal => Tag::Equal,
+                        ChangeTag::Delete => Tag::Delete,
+                        ChangeTag::Insert => Tag::Insert,
+                    },
+                    content: strip_line_break(change.value()).to_owned(),
+                })
+                .collect();
+            Hunk {
+                old_start: unified_start(old_range.start, old_range.len()),
+                old_lines: old_range.len(),
+                new_start: unified_start(new_range.start, new_range.len()),
+                new_lines: new_range.len(),
+                lines,
+            }
+        })
+        .collect()
+}
+
+/// Formats hunks as a unified diff, empty if there is no change.
+pub fn unified(old_name: &str, new_name: &str, hunks: &[Hunk]) -> String {
+    if hunks.is_empty() {
+        return String::new();
+    }
+    // assert: writing to a String never fails
+    let mut out = String::new();
+    writeln!(out, "--- {}\n+++ {}", old_name, new_name).unwrap();
+    for hunk in hunks {
+        writeln!(out, "{}", hunk.header()).unwrap();
+        for line in &hunk.lines {
+            writeln!(out, "{}{}", line.tag.prefix(), line.content).unwrap();
+        }
+    }
+    out
+}
+
+fn side_by_side_row(
+    html: &mut String,
+    old: Option<(usize, &DiffLine)>,
+    new: Option<(usize, &DiffLine)>,
+) {
+    html.push_str("<tr>");
+    let mut cell = |side: Option<(usize, &DiffLine)>| match side {
+        None => html.push_str(r#"<td class="n"></td><td class="e"></td>"#),
+        Some((n, line)) => {
+            let class = match line.tag {
+                Tag::Equal => "c",
+                Tag::Delete => "c d",
+                Tag::Insert => "c i",
+            };
+            write!(
+                html,
+                r#"<td class="n">{}</td><td class="{}">{}</td>"#,
+                n,
+                class,
+                escape(&line.content)
+            )
+            .unwrap();
+        }
+    };
+    cell(old);
+    cell(new);
+    html.push_str("</tr>\n");
+}
+
+/// Renders hunks side by side, pairing deleted lines with the inserted lines after them.
+pub fn render_side_by_side(old_name: &str, new_name: &str, hunks: &[Hunk]) -> String {
+    let mut html = String::new();
+    write!(
+        html,
+        r#"<!DOCTYPE html>
+<html>
+<head>
+<meta charset="utf-8">
+<title>{old} .. {new}</title>
+<style>
+body {{ margin: 0; font-family: sans-serif; }}
+table {{ border-collapse: collapse; width: 100%; table-layout: fixed; font: 13px/1.5 monospace; }}
+th {{ text-align: left; padding: 8px 16px; border-bottom: 1px solid #ddd; }}
+td.n {{ width: 4em; padding: 0 8px; text-align: right; color: #aaa; user-select: none; }}
+td.c, td.e {{ white-space: pre-wrap; word-break: break-all; padding-right: 16px; }}
+td.d {{ background: #ffebe9; }}
+td.i {{ background: #e6ffec; }}
+td.e {{ background: #f6f8fa; }}
+tr.h td {{ background: #ddf4ff; color: #555; padding: 4px 16px; }}
+</style>
+</head>
+<body>
+<table>
+<tr><th colspan="2">{old}</th><th colspan="2">{new}</th></tr>
+"#,
+        old = escape(old_name),
+        new = escape(new_name),
+    )
+    .unwrap();
+
+    if hunks.is_empty() {
+        html.push_str("<tr class=\"h\"><td colspan=\"4\">No changes</td></tr>\n");
+    }
+    for hunk in hunks {
+        writeln!(
+            html,
+            r#"<tr class="h"><td colspan="4">{}</td></tr>"#,
+            hunk.header()
+        )
+        .unwrap();
+        let (mut old_no, mut new_no) = (hunk.old_start, hunk.new_start);
+        let (mut deleted, mut inserted) = (Vec::new(), Vec::new());
+        // a trailing Equal flushes the last change block
+        let end = DiffLine {
+            tag: Tag::Equal,
+            content: String::new(),
+        };
+        for line in hunk.lines.iter().chain(Some(&end)) {
+            match line.tag {
+                Tag::Delete => deleted.push(line),
+                Tag::Insert => inserted.push(line),
+                Tag::Equal => {
+                    for i in 0..deleted.len().max(inserted.len()) {
+                        let old = deleted.get(i).map(|&l| (old_no + i, l));
+                        let new = inserted.get(i).map(|&l| (new_no + i, l));
+                        side_by_side_row(&mut html, old, new);
+                    }
+                    old_no += deleted.len();
+                    new_no += inserted.len();
+                    deleted.clear();
+                    inserted.clear();
+                    if !std::ptr::eq(line, &end) {
+                        side_by_side_row(&mut html, Some((old_no, line)), Some((new_no, line)));
+                        old_no += 1;
+                        new_no += 1;
+                    }
+                }
+            }
+        }
+    }
+
+    html.push_str("</table>\n</body>\n</html>\n");
+    html
+}
+
+#[cfg(test)]
+#[test]
+fn test_diff() {
+    let old = "a\nb\nc\nd\ne\nf\ng\nh\ni\nj\n";
+    let new = "a\nb\nC\nd\ne\nf\ng\nh\ni\nj\nk\n";
+    let changes = hunks(old, new, 1);
+    assert_eq!(changes.len(), 2);
+    assert_eq!((changes[0].old_start, changes[0].old_lines), (2, 3));
+    assert_eq!((changes[1].new_start, changes[1].new_lines), (10, 2));
+
+    assert_eq!(
+        unified("A", "B", &changes),
+        "--- A\n+++ B\n\
+         @@ -2,3 +2,3 @@\n b\n-c\n+C\n d\n\
+         @@ -10,1 +10,2 @@\n j\n+k\n"
+    );
+    assert_eq!(unified("A", "B", &hunks(old, old, 3)), "");
+
+    // into an empty content, with CRLF
+    let deleted = hunks("x\r\ny\r\n", "", 3);
+    assert_eq!((deleted[0].new_start, deleted[0].new_lines), (0, 0));
+    assert_eq!(deleted[0].lines[1].content, "y");
+
+    let html = render_side_by_side("<A>", "B", &changes);
+    assert!(html.contains("<th colspan=\"2\">&lt;A&gt;</th>"));
+    assert!(html.contains(
+        r#"<td class="n">3</td><td class="c d">c</td><td class="n">3</td><td class="c i">C</td>"#
+    ));
+    assert!(html.contains(
+        r#"<td class="n"></td><td class="e"></td><td class="n">11</td><td class="c i">k</td>"#
+    ));
+    assert!(html.contains(r#"<td class="n">4</td><td class="c">d</td><td class="n">4</td>"#));
+}
@@ -1,18 +1,76 @@
+use crate::data::diff::Hunk;
+use crate::data::record::{Binary, RecordFile};
 use crate::time::SecTime;
 
 use serde::{Deserialize, Serialize};
 
+#[derive(Deserialize, Serialize)]
+pub struct FileReq {
+    pub name: String,
+    #[serde(default)]
+    pub lang: String,
+    pub content: String,
+}
+
 #[derive(Deserialize, Serialize)]
 pub struct SaveRecordReq {
     pub title: String,
+    #[serde(default)]
     pub lang: String,
+    #[serde(default)]
     pub content: String,
+    #[serde(default)]
+    pub files: Vec<FileReq>,
     pub expiration: SecTime,
+    #[serde(default)]
+    pub burn_after_reading: bool,
+    #[serde(default)]
+    pub max_views: Option<u64>,
+    #[serde(default)]
+    pub password: Option<String>,
+    #[serde(default)]
+    pub encrypted: bool,
+    #[serde(default)]
+    pub encryption_version: Option<u32>,
+}
+
+#[derive(Deserialize)]
+pub struct ForkRecordReq {
+    pub expiration: SecTime,
+    #[serde(default)]
+    pub burn_after_reading: bool,
+    #[serde(default)]
+    pub max_views: Option<u64>,
+    #[serde(default)]
+    pub password: Option<String>,
 }
 
 #[derive(Serialize)]
 pub struct SaveRecordRes<'a> {
     pub key: &'a str,
+    pub delete_token: &'a str,
+    pub edit_token: &'a str,
+}
+
+#[derive(Deserialize)]
+pub struct EditRecordReq {
+    pub title: String,
+    #[serde(default)]
+    pub lang: String,
+    #[serde(default)]
+    pub content: String,
+    #[serde(default)]
+    pub files: Vec<FileReq>,
+}
+
+#[derive(Serialize)]
+pub struct EditRecordRes {
+    pub revision: usize,
+}
+
+#[derive(Deserialize)]
+pub struct PasswordQuery {
+    pub password: Option<String>,
 }
 
 #[derive(Serialize)]
@@ -23,6 +81,63 @@ pub struct FindRecordRes<'a> {
     pub saving_time: SecTime,
     pub expiration: SecTime,
     pub view_count: u64,
+    pub revision: usize,
+    pub burn_after_reading: bool,
+    pub max_views: Option<u64>,
+    pub encrypted: bool,
+    pub encryption_version: Option<u32>,
+    #[serde(skip_serializing_if = "<[_]>::is_empty")]
+    pub files: &'a [RecordFile],
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub binary: Option<&'a Binary>,
+    pub parent_key: Option<&'a str>,
+    pub fork_count: u64,
+}
+
+#[derive(Serialize)]
+pub struct FindRevisionRes<'a> {
+    pub title: &'a str,
+    pub lang: &'a str,
+    pub content: &'a str,
+    pub editing_time: SecTime,
+    pub revision: usize,
+    #[serde(skip_serializing_if = "<[_]>::is_empty")]
+    pub files: &'a [RecordFile],
+}
+
+#[derive(Deserialize)]
+pub struct DiffQuery {
+    pub format: Option<String>,
+    pub context: Option<usize>,
+}
+
+#[derive(Serialize)]
+pub struct DiffRes<'a> {
+    pub key_a: &'a str,
+    pub key_b: &'a str,
+    pub hunks: &'a [Hunk],
+}
+
+#[derive(Serialize)]
+pub struct HealthRes<'a> {
+    pub status: &'a str,
+}
+
+#[derive(Serialize, Default)]
+pub struct ReadyRes {
+    pub ready: bool,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub store_size: Option<usize>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub item_count: Option<usize>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub max_store_size: Option<usize>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub utilization: Option<f64>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub gc_running: Option<bool>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub error: Option<String>,
 }
 
 #[derive(Serialize, Debug)]
@@ -50,6 +165,45 @@ define_err_res!(record_not_found, 1002, "Can not find record");
 define_err_res!(too_long_expiration, 1003, "Too long expiration");
 define_err_res!(too_long_content, 1004, "Too long content");
 define_err_res!(redis_error, 1005, "Redis error");
+define_err_res!(wrong_token, 1006, "Wrong token");
+define_err_res!(too_many_revisions, 1007, "Too many revisions");
+define_err_res!(revision_not_found, 1008, "Can not find revision");
+define_err_res!(limited_views, 1009, "Views are limited");
+define_err_res!(bad_max_views, 1010, "Max views must be positive");
+define_err_res!(password_required, 1011, "Password required");
+define_err_res!(wrong_password, 1012, "Wrong password");
+define_err_res!(bad_ciphertext, 1013, "Encrypted content must be base64");
+define_err_res!(
+    bad_encryption_version,
+    1014,
+    "Unsupported encryption version"
+);
+define_err_res!(seal_error, 1015, "Can not decrypt record");
+define_err_res!(storage_error, 1016, "Storage error");
+define_err_res!(
+    encrypted_record,
+    1017,
+    "Encrypted records can not be rendered"
+);
+define_err_res!(unknown_lang, 1018, "Unknown language");
+define_err_res!(bad_file_name, 1019, "Bad or duplicate file name");
+define_err_res!(too_many_files, 1020, "Too many files");
+define_err_res!(file_not_found, 1021, "Can not find file");
+define_err_res!(content_with_files, 1022, "Content and files are exclusive");
+define_err_res!(bad_multipart, 1023, "Bad multipart body");
+define_err_res!(too_large_file, 1024, "Too large file");
+define_err_res!(binary_record, 1025, "Not supported for binary records");
+define_err_res!(bad_diff_format, 1026, "Unknown diff format");
+define_err_res!(rate_limited, 1027, "Too many requests");
+define_err_res!(denied_content, 1028, "Content denied by policy");
+define_err_res!(banned_content, 1029, "Banned content");
+define_err_res!(too_long_line, 1030, "Too long line");
+define_err_res!(garbage_content, 1031, "Binary or garbage content");
+define_err_res!(
+    bundle_record,
+    1032,
+    "Not supported for bundles, read their files or archive"
+);
 
 #[cfg(test)]
 #[test]
@@ -59,4 +213,32 @@ fn test_err_res() {
     p(ErrRes::record_not_found());
     p(ErrRes::too_long_expiration());
     p(ErrRes::too_long_content());
+    p(ErrRes::redis_error());
+    p(ErrRes::wrong_token());
+    p(ErrRes::too_many_revisions());
+    p(ErrRes::revision_not_found());
+    p(ErrRes::limited_views());
+    p(ErrRes::bad_max_views());
+    p(ErrRes::password_required());
+    p(ErrRes::wrong_password());
+    p(ErrRes::bad_ciphertext());
+    p(ErrRes::bad_encryption_version());
+    p(ErrRes::seal_error());
+    p(ErrRes::storage_error());
+    p(ErrRes::encrypted_record());
+    p(ErrRes::unknown_lang());
+    p(ErrRes::bad_file_name());
+    p(ErrRes::too_many_files());
+    p(ErrRes::file_not_found());
+    p(ErrRes::content_with_files());
+    p(ErrRes::bad_multipart());
+    p(ErrRes::too_large_file());
+    p(ErrRes::binary_record());
+    p(ErrRes::bad_diff_format());
+    p(ErrRes::rate_limited());
+    p(ErrRes::denied_content());
+    p(ErrRes::banned_content());
+    p(ErrRes::too_long_line());
+    p(ErrRes::garbage_content());
+    p(ErrRes::bundle_record());
 }
@@ -0,0 +1,28 @@
+//! Client-side encrypted records.
+//!
+//! The content of an encrypted record is an opaque base64 blob. The key never
+//! reaches the server, so the server only checks the blob is well-formed and
+//! returns it verbatim.
+
+pub const SUPPORTED_VERSIONS: &[u32] = &[1];
+pub const DEFAULT_VERSION: u32 = 1;
+
+#[inline]
+pub fn is_supported_version(version: u32) -> bool {
+    SUPPORTED_VERSIONS.contains(&version)
+}
+
+pub fn is_valid_ciphertext(content: &str) -> bool {
+    base64::decode(content).is_ok()
+}
+
+#[cfg(test)]
+#[test]
+fn test_ciphertext() {
+    assert!(is_valid_ciphertext("aGVsbG8="));
+    assert!(is_valid_ciphertext(""));
+    assert!(!is_valid_ciphertext("hello world"));
+    assert!(!is_valid_ciphertext("aGVs*G8="));
+    assert!(is_supported_version(DEFAULT_VERSION));
+    assert!(!is_supported_version(0));
+}
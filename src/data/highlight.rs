@@ -0,0 +1,151 @@
+//! Rendering of records as standalone HTML pages.
+
+use std::fmt::Write;
+
+use crate::data::lang::lookup;
+
+use syntect::easy::HighlightLines;
+use syntect::highlighting::{Color, Theme, ThemeSet};
+use syntect::html::{styled_line_to_highlighted_html, IncludeBackground};
+use syntect::parsing::{SyntaxReference, SyntaxSet};
+use syntect::util::LinesWithEndings;
+
+const THEME: &str = "InspiredGitHub";
+
+lazy_static! {
+    static ref SYNTAX_SET: SyntaxSet = SyntaxSet::load_defaults_newlines();
+    static ref THEME_SET: ThemeSet = ThemeSet::load_defaults();
+}
+
+// highlights `#L10` or `#L10-L20`
+const SCRIPT: &str = r#"
+function mark() {
+  document.querySelectorAll("tr.hl").forEach(function (tr) { tr.className = ""; });
+  var m = /^#L(\d+)(?:-L(\d+))?$/.exec(location.hash);
+  if (!m) return;
+  var from = +m[1], to = +(m[2] || m[1]);
+  for (var i = Math.min(from, to); i <= Math.max(from, to); i++) {
+    var tr = document.getElementById("L" + i);
+    if (tr) tr.className = "hl";
+  }
+}
+window.addEventListener("hashchange", mark);
+mark();
+"#;
+
+pub fn escape(s: &str) -> String {
+    let mut out = String::with_capacity(s.len());
+    for c in s.chars() {
+        match c {
+            '&' => out.push_str("&amp;"),
+            '<' => out.push_str("&lt;"),
+            '>' => out.push_str("&gt;"),
+            '"' => out.push_str("&quot;"),
+            '\'' => out.push_str("&#39;"),
+            c => out.push(c),
+        }
+    }
+    out
+}
+
+fn css_color(c: Color) -> String {
+    format!("#{:02x}{:02x}{:02x}", c.r, c.g, c.b)
+}
+
+/// Falls back to plain text for an unknown `lang`.
+fn find_syntax(lang: &str) -> &'static SyntaxReference {
+    SYNTAX_SET
+        .find_syntax_by_token(lookup(lang).map_or(lang, |l| l.syntax))
+        .unwrap_or_else(|| SYNTAX_SET.find_syntax_plain_text())
+}
+
+fn highlight_lines(syntax: &SyntaxReference, theme: &Theme, content: &str) -> Vec<String> {
+    let mut h = HighlightLines::new(syntax, theme);
+    LinesWithEndings::from(content)
+        .map(|line| {
+            h.highlight_line(line, &SYNTAX_SET)
+                .and_then(|ranges| styled_line_to_highlighted_html(&ranges, IncludeBackground::No))
+                .unwrap_or_else(|_| escape(line))
+        })
+        .collect()
+}
+
+/// Renders a page with line numbers, `#L<n>` anchors and a link to `raw_href`.
+pub fn render_page(title: &str, lang: &str, content: &str, raw_href: &str) -> String {
+    // assert: THEME is bundled
+    let theme = &THEME_SET.themes[THEME];
+    let syntax = find_syntax(lang);
+    let background = theme.settings.background.map(css_color);
+    let background = background.as_deref().unwrap_or("#ffffff");
+
+    let mut html = String::with_capacity(content.len() * 4);
+    // assert: writing to a String never fails
+    write!(
+        html,
+        r#"<!DOCTYPE html>
+<html>
+<head>
+<meta charset="utf-8">
+<title>{title}</title>
+<style>
+body {{ margin: 0; font-family: sans-serif; background: {background}; }}
+header {{ padding: 8px 16px; border-bottom: 1px solid #ddd; }}
+header span {{ color: #888; margin: 0 8px; }}
+table {{ border-collapse: collapse; font: 13px/1.5 monospace; }}
+td.n {{ padding: 0 8px 0 16px; text-align: right; user-select: none; }}
+td.n a {{ color: #aaa; text-decoration: none; }}
+td.c {{ white-space: pre; padding-right: 16px; }}
+tr.hl {{ background: #fff8c5; }}
+</style>
+</head>
+<body>
+<header><strong>{title}</strong><span>{lang}</span><a href="{raw_href}">raw</a></header>
+<table>
+"#,
+        title = escape(title),
+        background = background,
+        lang = escape(&syntax.name),
+        raw_href = escape(raw_href),
+    )
+    .unwrap();
+
+    for (i, line) in highlight_lines(syntax, theme, content).iter().enumerate() {
+        let n = i + 1;
+        writeln!(
+            html,
+            r##"<tr id="L{n}"><td class="n"><a href="#L{n}">{n}</a></td><td class="c">{line}</td></tr>"##,
+            n = n,
+            line = line.replace(&['\r', '\n'][..], ""),
+        )
+        .unwrap();
+    }
+
+    write!(
+        html,
+        "</table>\n<script>{}</script>\n</body>\n</html>\n",
+        SCRIPT
+    )
+    .unwrap();
+    html
+}
+
+#[cfg(test)]
+#[test]
+fn test_render_page() {
+    let html = render_page(
+        "<t>",
+        "rs",
+        "fn main() {}\nlet x = \"<b>\";\n",
+        "raw?a=1&b=2",
+    );
+    assert!(html.contains("<title>&lt;t&gt;</title>"));
+    assert!(html.contains(r##"<tr id="L2"><td class="n"><a href="#L2">2</a>"##));
+    assert!(!html.contains("L3"));
+    assert!(!html.contains("<b>"));
+    assert!(html.contains(r#"href="raw?a=1&amp;b=2""#));
+    assert!(html.contains("<span>Rust</span>"));
+
+    let html = render_page("t", "no-such-lang", "<x>", "raw");
+    assert!(html.contains("<span>Plain Text</span>"));
+    assert!(html.contains("&lt;x&gt;"));
+}
@@ -6,7 +6,7 @@ use short_crypt::ShortCrypt;
 const VALID_BYTES: usize = 8; // take first 8 bytes of NanoTime
 
 lazy_static! {
-    static ref SC: ShortCrypt = { ShortCrypt::new(&*CRYPT_KEY) };
+    static ref SC: ShortCrypt = ShortCrypt::new(&*CRYPT_KEY);
 }
 
 #[inline]
@@ -0,0 +1,393 @@
+//! The registry of languages accepted in `lang`.
+//!
+//! Requests may use any alias in any case; records store the canonical id.
+//! An empty `lang` or "auto" is guessed from content.
+
+pub struct Lang {
+    pub id: &'static str,
+    pub aliases: &'static [&'static str],
+    pub syntax: &'static str, // a syntect token, see `data::highlight`
+    pub mime: &'static str,
+}
+
+const TEXT_MIME: &str = "text/plain; charset=utf-8";
+
+macro_rules! lang {
+    ($id:expr, [$($alias:expr),*], $syntax:expr) => {
+        lang!($id, [$($alias),*], $syntax, TEXT_MIME)
+    };
+    ($id:expr, [$($alias:expr),*], $syntax:expr, $mime:expr) => {
+        Lang {
+            id: $id,
+            aliases: &[$($alias),*],
+            syntax: $syntax,
+            mime: $mime,
+        }
+    };
+}
+
+pub const PLAINTEXT: &str = "plaintext";
+const AUTO: &str = "auto";
+
+// Types that a browser would render or execute (html, svg, javascript, ...)
+// are served as plain text.
+pub static LANGS: &[Lang] = &[
+    lang!(PLAINTEXT, ["text", "txt", "plain"], "txt"),
+    lang!("bash", ["sh", "shell", "zsh"], "sh"),
+    lang!("c", ["h"], "c"),
+    lang!("cpp", ["c++", "cc", "cxx", "hpp"], "cpp"),
+    lang!("csharp", ["c#", "cs"], "cs"),
+    lang!("css", [], "css"),
+    lang!("csv", [], "csv", "text/csv; charset=utf-8"),
+    lang!("diff", ["patch"], "diff", "text/x-diff; charset=utf-8"),
+    lang!("go", ["golang"], "go"),
+    lang!("haskell", ["hs"], "hs"),
+    lang!("html", ["htm", "xhtml"], "html"),
+    lang!("java", [], "java"),
+    lang!("javascript", ["js", "node", "mjs", "jsx"], "js"),
+    lang!("json", [], "json", "application/json; charset=utf-8"),
+    lang!("lua", [], "lua"),
+    lang!("makefile", ["make", "mk"], "makefile"),
+    lang!("markdown", ["md"], "md", "text/markdown; charset=utf-8"),
+    lang!("perl", ["pl"], "pl"),
+    lang!("php", [], "php"),
+    lang!("python", ["py", "python3", "py3"], "py"),
+    lang!("ruby", ["rb"], "rb"),
+    lang!("rust", ["rs"], "rs"),
+    lang!("sql", [], "sql"),
+    lang!("toml", [], "toml", "application/toml; charset=utf-8"),
+    lang!("typescript", ["ts", "tsx"], "ts"),
+    lang!("xml", ["svg"], "xml"),
+    lang!("yaml", ["yml"], "yaml", "application/yaml; charset=utf-8"),
+];
+
+/// Finds a language by id or alias, ignoring case and surrounding spaces.
+pub fn lookup(lang: &str) -> Option<&'static Lang> {
+    let lang = lang.trim().to_ascii_lowercase();
+    LANGS
+        .iter()
+        .find(|l| l.id == lang || l.aliases.contains(&lang.as_str()))
+}
+
+/// The canonical id of a stored `lang`, which may predate the registry.
+pub fn canonical_id(lang: &str) -> &'static str {
+    lookup(lang).map_or(PLAINTEXT, |l| l.id)
+}
+
+/// Resolves a requested `lang` to a canonical id, guessing from `content` if asked to.
+/// Ciphertext is never guessed from. Returns `None` for an unknown language.
+pub fn resolve(lang: &str, content: &str, encrypted: bool) -> Option<&'static str> {
+    let trimmed = lang.trim();
+    if trimmed.is_empty() || trimmed.eq_ignore_ascii_case(AUTO) {
+        return Some(if encrypted {
+            PLAINTEXT
+        } else {
+            detect(content)
+        });
+    }
+    lookup(trimmed).map(|l| l.id)
+}
+
+/// Like `resolve`, but guesses from the extension of a file `name` before its content.
+pub fn resolve_named(
+    lang: &str,
+    name: &str,
+    content: &str,
+    encrypted: bool,
+) -> Option<&'static str> {
+    let trimmed = lang.trim();
+    if trimmed.is_empty() || trimmed.eq_ignore_ascii_case(AUTO) {
+        if let Some(lang) = from_file_name(name) {
+            return Some(lang);
+        }
+    }
+    resolve(lang, content, encrypted)
+}
+
+fn from_file_name(name: &str) -> Option<&'static str> {
+    if name.eq_ignore_ascii_case("makefile") {
+        return Some("makefile");
+    }
+    let (_, ext) = name.rsplit_once('.')?;
+    lookup(ext).map(|l| l.id)
+}
+
+/// Maps `lang` to the MIME type of raw content.
+pub fn mime_type(lang: &str) -> &'static str {
+    lookup(lang).map_or(TEXT_MIME, |l| l.mime)
+}
+
+/// Guesses the language of `content` from a shebang, a modeline, its format
+/// or keyword statistics, in that order.
+pub fn detect(content: &str) -> &'static str {
+    from_shebang(content)
+        .or_else(|| from_modeline(content))
+        .or_else(|| from_format(content))
+        .or_else(|| from_keywords(content))
+        .unwrap_or(PLAINTEXT)
+}
+
+fn from_shebang(content: &str) -> Option<&'static str> {
+    let line = content.lines().next()?.strip_prefix("#!")?;
+    let mut args = line.split_whitespace();
+    let mut program = args.next()?.rsplit('/').next()?;
+    if program == "env" {
+        program = args.find(|arg| !arg.starts_with('-'))?;
+    }
+    // python3.8 -> python
+    let program = program.trim_end_matches(|c: char| c.is_ascii_digit() || c == '.');
+    let lang = match program {
+        "sh" | "bash" | "zsh" | "dash" | "ksh" => "bash",
+        "node" | "nodejs" | "deno" => "javascript",
+        program => program,
+    };
+    lookup(lang).map(|l| l.id)
+}
+
+/// Reads vim (`vim: set ft=rust:`) and emacs (`-*- mode: rust -*-`) modelines
+/// in the first and last five lines.
+fn from_modeline(content: &str) -> Option<&'static str> {
+    let lines: Vec<&str> = content.lines().collect();
+    let tail = lines.len().saturating_sub(5).max(5.min(lines.len()));
+    lines[..5.min(lines.len())]
+        .iter()
+        .chain(&lines[tail..])
+        .find_map(|line| vim_modeline(line).or_else(|| emacs_modeline(line)))
+}
+
+fn vim_modeline(line: &str) -> Option<&'static str> {
+    let rest = &line[line.find("vim:").or_else(|| line.find("vi:"))?..];
+    rest.split(|c: char| c.is_whitespace() || c == ':')
+        .find_map(|opt| {
+            opt.strip_prefix("ft=")
+                .or_else(|| opt.strip_prefix("filetype="))
+                .or_else(|| opt.strip_prefix("syntax="))
+        })
+        .and_then(lookup)
+        .map(|l| l.id)
+}
+
+fn emacs_modeline(line: &str) -> Option<&'static str> {
+    let start = line.find("-*-")? + 3;
+    let end = start + line[start..].find("-*-")?;
+    let vars = &line[start..end];
+    let mode = if vars.contains(':') {
+        vars.split(';').find_map(|var| {
+            let mut kv = var.splitn(2, ':');
+            match (kv.next()?.trim(), kv.next()?) {
+                ("mode", v) => Some(v),
+                _ => None,
+            }
+        })?
+    } else {
+        vars
+    };
+    lookup(mode.trim().trim_end_matches("-mode")).map(|l| l.id)
+}
+
+fn from_format(content: &str) -> Option<&'static str> {
+    let trimmed = content.trim_start();
+    if trimmed.starts_with("<?php") {
+        return Some("php");
+    }
+    if trimmed.starts_with("<?xml") {
+        return Some("xml");
+    }
+    let head = trimmed.get(..15).unwrap_or(trimmed).to_ascii_lowercase();
+    if head.starts_with("<!doctype html") || head.starts_with("<html") {
+        return Some("html");
+    }
+    if (trimmed.starts_with('{') || trimmed.starts_with('['))
+        && serde_json::from_str::<serde_json::Value>(trimmed).is_ok()
+    {
+        return Some("json");
+    }
+    if trimmed.starts_with("diff --git")
+        || (trimmed.starts_with("--- ") && content.contains("\n+++ ") && content.contains("\n@@"))
+    {
+        return Some("diff");
+    }
+    None
+}
+
+// distinctive substrings, each occurrence counts once up to MAX_HITS
+static KEYWORDS: &[(&str, &[&str])] = &[
+    (
+        "rust",
+        &[
+            "fn ",
+            "let mut ",
+            "impl ",
+            "pub fn",
+            "&self",
+            "println!",
+            "-> ",
+            "use std::",
+        ],
+    ),
+    (
+        "python",
+        &[
+            "def ", "import ", "self.", "elif ", "None", "print(", "__init__", "):\n",
+        ],
+    ),
+    (
+        "javascript",
+        &[
+            "function ",
+            "const ",
+            "=> ",
+            "console.log",
+            "let ",
+            "require(",
+            "===",
+            "document.",
+        ],
+    ),
+    (
+        "go",
+        &[
+            "func ",
+            "package ",
+            ":= ",
+            "fmt.",
+            "go func",
+            "chan ",
+            "err != nil",
+        ],
+    ),
+    (
+        "java",
+        &[
+            "public class",
+            "System.out",
+            "private ",
+            "void ",
+            "import java",
+            "@Override",
+            "new ",
+        ],
+    ),
+    (
+        "cpp",
+        &[
+            "std::",
+            "#include <iostream>",
+            "cout <<",
+            "template <",
+            "nullptr",
+            "namespace ",
+        ],
+    ),
+    (
+        "c",
+        &["#include <", "printf(", "int main", "malloc(", "NULL", "->"],
+    ),
+    (
+        "bash",
+        &[
+            "echo ", "\nfi", "then\n", "$(", "\ndone", "esac", "export ", "${",
+        ],
+    ),
+    (
+        "sql",
+        &[
+            "SELECT ",
+            "FROM ",
+            "WHERE ",
+            "INSERT INTO",
+            "CREATE TABLE",
+            "JOIN ",
+        ],
+    ),
+    (
+        "ruby",
+        &[
+            "\nend",
+            "def ",
+            "puts ",
+            "require '",
+            "do |",
+            "elsif ",
+            "attr_",
+        ],
+    ),
+    (
+        "css",
+        &["px;", "color:", "margin:", "padding:", "font-", "display:"],
+    ),
+];
+
+const MAX_HITS: usize = 5;
+const MIN_SCORE: usize = 2;
+
+fn from_keywords(content: &str) -> Option<&'static str> {
+    let score = |keywords: &[&str]| -> usize {
+        keywords
+            .iter()
+            .map(|k| content.matches(k).take(MAX_HITS).count())
+            .sum()
+    };
+    let (lang, best) = KEYWORDS
+        .iter()
+        .map(|&(lang, keywords)| (lang, score(keywords)))
+        .fold(("", 0), |best, cur| if cur.1 > best.1 { cur } else { best });
+    if best >= MIN_SCORE {
+        Some(lang)
+    } else {
+        None
+    }
+}
+
+#[cfg(test)]
+#[test]
+fn test_lang() {
+    assert_eq!(mime_type("JSON "), "application/json; charset=utf-8");
+    assert_eq!(mime_type("yml"), "application/yaml; charset=utf-8");
+    assert_eq!(mime_type("html"), TEXT_MIME);
+    assert_eq!(mime_type(""), TEXT_MIME);
+
+    assert_eq!(resolve("JavaScript ", "", false), Some("javascript"));
+    assert_eq!(resolve("js", "", false), Some("javascript"));
+    assert_eq!(resolve("cobol", "", false), None);
+    assert_eq!(
+        resolve("auto", "fn main() -> i32 { 0 }", true),
+        Some(PLAINTEXT)
+    );
+    assert_eq!(canonical_id("no-such-lang"), PLAINTEXT);
+    assert_eq!(resolve_named("", "main.RS", "", true), Some("rust"));
+    assert_eq!(resolve_named("", "Makefile", "", false), Some("makefile"));
+    assert_eq!(
+        resolve_named("", "a.x", "def f():\n  pass\n", false),
+        Some("python")
+    );
+    assert_eq!(resolve_named("go", "main.rs", "", false), Some("go"));
+
+    for l in LANGS {
+        assert_eq!(lookup(l.id).unwrap().id, l.id);
+        assert!(KEYWORDS.iter().all(|&(id, _)| lookup(id).is_some()));
+    }
+
+    let cases = [
+        ("#!/usr/bin/env python3\nprint(1)\n", "python"),
+        ("#!/bin/sh\necho hi\n", "bash"),
+        ("#!/usr/bin/env -S node --harmony\n", "javascript"),
+        ("x = 1\n# vim: set ft=ruby :\n", "ruby"),
+        ("-- -*- mode: sql -*-\nx\n", "sql"),
+        ("// -*- rust -*-\n", "rust"),
+        ("{\"a\": [1, 2]}", "json"),
+        ("<?php echo 1;", "php"),
+        ("<!DOCTYPE html>\n<html></html>", "html"),
+        ("--- a/x\n+++ b/x\n@@ -1 +1 @@\n-a\n+b\n", "diff"),
+        ("fn main() {\n    let mut x = 1;\n}\n", "rust"),
+        ("def f(x):\n    return x + 1\n", "python"),
+        ("package main\n\nfunc main() {\n\tx := 1\n}\n", "go"),
+        ("SELECT a FROM t WHERE b = 1;", "sql"),
+        ("just some words", PLAINTEXT),
+        ("", PLAINTEXT),
+    ];
+    for &(content, lang) in cases.iter() {
+        assert_eq!(detect(content), lang, "{:?}", content);
+        assert_eq!(resolve(" ", content, false), Some(lang));
+    }
+}
@@ -1,3 +1,15 @@
+pub mod archive;
+pub mod binary;
+pub mod compress;
+pub mod diff;
 pub mod dto;
+pub mod encryption;
+pub mod highlight;
 pub mod key;
+pub mod lang;
+pub mod multipart;
+pub mod password;
+pub mod policy;
 pub mod record;
+pub mod seal;
+pub mod token;
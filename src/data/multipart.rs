@@ -0,0 +1,168 @@
+//! A minimal parser of buffered `multipart/form-data` bodies.
+
+use std::str;
+
+pub struct Part<'a> {
+    pub name: String,
+    pub file_name: Option<String>,
+    pub content_type: Option<String>,
+    pub body: &'a [u8],
+}
+
+fn find(haystack: &[u8], needle: &[u8]) -> Option<usize> {
+    haystack.windows(needle.len()).position(|w| w == needle)
+}
+
+/// Splits header parameters on `;` outside quoted strings.
+fn split_params(s: &str) -> Vec<&str> {
+    let mut params = Vec::new();
+    let (mut start, mut quoted, mut escaped) = (0, false, false);
+    for (i, c) in s.char_indices() {
+        match c {
+            _ if escaped => escaped = false,
+            '\\' if quoted => escaped = true,
+            '"' => quoted = !quoted,
+            ';' if !quoted => {
+                params.push(&s[start..i]);
+                start = i + 1;
+            }
+            _ => {}
+        }
+    }
+    params.push(&s[start..]);
+    params
+}
+
+fn unquote(s: &str) -> String {
+    let s = s.trim();
+    match s.strip_prefix('"').and_then(|s| s.strip_suffix('"')) {
+        None => s.to_owned(),
+        Some(inner) => {
+            let mut out = String::with_capacity(inner.len());
+            let mut chars = inner.chars();
+            while let Some(c) = chars.next() {
+                out.push(if c == '\\' {
+                    chars.next().unwrap_or(c)
+                } else {
+                    c
+                });
+            }
+            out
+        }
+    }
+}
+
+/// Extracts the boundary from a `Content-Type` header.
+pub fn boundary(content_type: &str) -> Option<String> {
+    let params = split_params(content_type);
+    if !params[0].trim().eq_ignore_ascii_case("multipart/form-data") {
+        return None;
+    }
+    params[1..]
+        .iter()
+        .find_map(|p| {
+            let (k, v) = p.split_once('=')?;
+            if k.trim().eq_ignore_ascii_case("boundary") {
+                Some(unquote(v))
+            } else {
+                None
+            }
+        })
+        .filter(|b| !b.is_empty() && b.len() <= 70) // RFC 2046
+}
+
+fn parse_part(raw: &[u8]) -> Option<Part<'_>> {
+    let head_end = find(raw, b"\r\n\r\n")?;
+    let head = str::from_utf8(&raw[..head_end]).ok()?;
+    let mut name = None;
+    let mut file_name = None;
+    let mut content_type = None;
+    for line in head.split("\r\n") {
+        let (key, value) = line.split_once(':')?;
+        match key.trim().to_ascii_lowercase().as_str() {
+            "content-disposition" => {
+                let params = split_params(value);
+                if !params[0].trim().eq_ignore_ascii_case("form-data") {
+                    return None;
+                }
+                for p in &params[1..] {
+                    let (k, v) = p.split_once('=')?;
+                    match k.trim().to_ascii_lowercase().as_str() {
+                        "name" => name = Some(unquote(v)),
+                        "filename" => file_name = Some(unquote(v)),
+                        _ => {}
+                    }
+                }
+            }
+            "content-type" => content_type = Some(value.trim().to_owned()),
+            _ => {}
+        }
+    }
+    Some(Part {
+        name: name?,
+        file_name,
+        content_type,
+        body: &raw[head_end + 4..],
+    })
+}
+
+/// Splits `body` into its parts. Returns `None` if it is malformed.
+pub fn parse<'a>(body: &'a [u8], boundary: &str) -> Option<Vec<Part<'a>>> {
+    let delimiter = format!("\r\n--{}", boundary);
+    let delimiter = delimiter.as_bytes();
+
+    // the first delimiter may come without CRLF, or after a preamble
+    let mut rest = match body.strip_prefix(&delimiter[2..]) {
+        Some(rest) => rest,
+        None => &body[find(body, delimiter)? + delimiter.len()..],
+    };
+    let mut parts = Vec::new();
+    loop {
+        if rest.starts_with(b"--") {
+            return Some(parts);
+        }
+        rest = rest.strip_prefix(b"\r\n")?;
+        let end = find(rest, delimiter)?;
+        parts.push(parse_part(&rest[..end])?);
+        rest = &rest[end + delimiter.len()..];
+    }
+}
+
+#[cfg(test)]
+#[test]
+fn test_multipart() {
+    assert_eq!(
+        boundary("multipart/form-data; boundary=\"a;b\"").unwrap(),
+        "a;b"
+    );
+    assert_eq!(boundary("Multipart/Form-Data;boundary=xyz").unwrap(), "xyz");
+    assert!(boundary("application/json").is_none());
+    assert!(boundary("multipart/form-data").is_none());
+
+    let body = b"preamble\r\n--xyz\r\n\
+        Content-Disposition: form-data; name=\"title\"\r\n\r\n\
+        hello\r\n--xyz\r\n\
+        content-disposition: form-data; name=\"file\"; filename=\"a;\\\"b\\\".bin\"\r\n\
+        Content-Type: application/octet-stream\r\n\r\n\
+        \x00\r\n--xy\xff\r\n--xyz--\r\n";
+    let parts = parse(body, "xyz").unwrap();
+    assert_eq!(parts.len(), 2);
+    assert_eq!(parts[0].name, "title");
+    assert_eq!(parts[0].body, b"hello");
+    assert!(parts[0].file_name.is_none());
+    assert_eq!(parts[1].name, "file");
+    assert_eq!(parts[1].file_name.as_deref(), Some("a;\"b\".bin"));
+    assert_eq!(
+        parts[1].content_type.as_deref(),
+        Some("application/octet-stream")
+    );
+    assert_eq!(parts[1].body, b"\x00\r\n--xy\xff");
+
+    assert_eq!(parse(b"--xyz--\r\n", "xyz").unwrap().len(), 0);
+    assert!(parse(b"--xyz\r\nno header\r\n--xyz--", "xyz").is_none());
+    assert!(parse(
+        b"--xyz\r\nContent-Disposition: form-data; name=a\r\n\r\nx",
+        "xyz"
+    )
+    .is_none());
+}
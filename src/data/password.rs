@@ -0,0 +1,25 @@
+use argon2::Config;
+use rand::Rng;
+
+const SALT_LEN: usize = 16;
+
+/// Salted argon2 hash in the PHC string format.
+pub fn hash_password(password: &str) -> String {
+    let salt: [u8; SALT_LEN] = rand::thread_rng().gen();
+    // assert: default config and salt length are valid
+    argon2::hash_encoded(password.as_bytes(), &salt, &Config::default()).unwrap()
+}
+
+pub fn verify_password(hash: &str, password: &str) -> bool {
+    argon2::verify_encoded(hash, password.as_bytes()).unwrap_or(false)
+}
+
+#[cfg(test)]
+#[test]
+fn test_password() {
+    let hash = hash_password("secret");
+    assert_ne!(hash, hash_password("secret"));
+    assert!(verify_password(&hash, "secret"));
+    assert!(!verify_password(&hash, "Secret"));
+    assert!(!verify_password("", "secret"));
+}
@@ -0,0 +1,279 @@
+//! Content policy checked before a record is saved or edited, configured from a JSON file.
+
+use crate::data::dto::ErrRes;
+use crate::data::record::RecordFile;
+use crate::env::POLICY_PATH;
+
+use std::collections::HashSet;
+use std::fs;
+
+use regex::RegexSet;
+use serde::Deserialize;
+use sha2::{Digest, Sha256};
+
+lazy_static! {
+    pub static ref POLICY: Policy = match *POLICY_PATH {
+        None => Policy::default(),
+        Some(ref path) => Policy::load(path).expect("Invalid PASTEBIN_POLICY_PATH"),
+    };
+}
+
+#[derive(Deserialize, Default)]
+#[serde(default, deny_unknown_fields)]
+struct PolicyConfig {
+    deny_patterns: Vec<String>,
+    banned_hashes: Vec<String>,
+    max_line_length: Option<usize>,
+    reject_garbage: bool,
+}
+
+/// A piece of a record as filters see it.
+pub enum Part<'a> {
+    /// A title or a file name, only matched.
+    Label(&'a str),
+    /// A plaintext content.
+    Text(&'a str),
+    /// The bytes of a binary record, or a ciphertext as sent, only hashed.
+    Opaque(&'a [u8]),
+}
+
+/// A rule of the policy. Fails with the `ErrRes` the client gets.
+pub trait ContentFilter: Send + Sync {
+    fn check(&self, part: &Part<'_>) -> Result<(), ErrRes<'static>>;
+}
+
+struct DenyList(RegexSet);
+
+impl ContentFilter for DenyList {
+    fn check(&self, part: &Part<'_>) -> Result<(), ErrRes<'static>> {
+        match part {
+            Part::Label(s) | Part::Text(s) if self.0.is_match(s) => Err(ErrRes::denied_content()),
+            _ => Ok(()),
+        }
+    }
+}
+
+struct BannedHashes(HashSet<[u8; 32]>);
+
+impl ContentFilter for BannedHashes {
+    fn check(&self, part: &Part<'_>) -> Result<(), ErrRes<'static>> {
+        let bytes = match part {
+            Part::Label(_) => return Ok(()),
+            Part::Text(s) => s.as_bytes(),
+            Part::Opaque(bytes) => bytes,
+        };
+        let digest: [u8; 32] = Sha256::digest(bytes).into();
+        if self.0.contains(&digest) {
+            return Err(ErrRes::banned_content());
+        }
+        Ok(())
+    }
+}
+
+/// Counted in chars.
+struct MaxLineLength(usize);
+
+impl ContentFilter for MaxLineLength {
+    fn check(&self, part: &Part<'_>) -> Result<(), ErrRes<'static>> {
+        let text = match part {
+            Part::Text(s) => s,
+            _ => return Ok(()),
+        };
+        let too_long = text
+            .lines()
+            .any(|line| line.len() > self.0 && line.chars().count() > self.0);
+        if too_long {
+            return Err(ErrRes::too_long_line());
+        }
+        Ok(())
+    }
+}
+
+/// Rejects text with a NUL or with more than 1 in 20 control chars, which is not a paste.
+struct GarbageDetector;
+
+fn is_garbage(text: &str) -> bool {
+    let (mut total, mut control) = (0, 0);
+    for c in text.chars() {
+        match c {
+            '\0' => return true,
+            '\t' | '\n' | '\r' | '\x0c' => {}
+            c if c.is_control() => control += 1,
+            _ => {}
+        }
+        total += 1;
+    }
+    control * 20 > total
+}
+
+impl ContentFilter for GarbageDetector {
+    fn check(&self, part: &Part<'_>) -> Result<(), ErrRes<'static>> {
+        match part {
+            Part::Text(s) if is_garbage(s) => Err(ErrRes::garbage_content()),
+            _ => Ok(()),
+        }
+    }
+}
+
+/// Filters run in the order of the config fields. Empty if no config file is set.
+#[derive(Default)]
+pub struct Policy {
+    filters: Vec<Box<dyn ContentFilter>>,
+}
+
+/// What is about to be stored, after languages are resolved.
+pub struct Submission<'a> {
+    pub title: &'a str,
+    pub content: &'a str,
+    pub files: &'a [RecordFile],
+    pub encrypted: bool,
+    /// `content` is the base64 of uploaded bytes.
+    pub binary: bool,
+}
+
+impl Policy {
+    pub fn parse(json: &str) -> Result<Self, String> {
+        let config: PolicyConfig = serde_json::from_str(json).map_err(|e| e.to_string())?;
+        let mut filters: Vec<Box<dyn ContentFilter>> = Vec::new();
+
+        if !config.deny_patterns.is_empty() {
+            let set = RegexSet::new(&config.deny_patterns).map_err(|e| e.to_string())?;
+            filters.push(Box::new(DenyList(set)));
+        }
+
+        if !config.banned_hashes.is_empty() {
+            let mut hashes = HashSet::new();
+            for hex in &config.banned_hashes {
+                hashes.insert(parse_sha256(hex).ok_or_else(|| format!("bad hash: {}", hex))?);
+            }
+            filters.push(Box::new(BannedHashes(hashes)));
+        }
+
+        if let Some(max) = config.max_line_length {
+            filters.push(Box::new(MaxLineLength(max)));
+        }
+
+        if config.reject_garbage {
+            filters.push(Box::new(GarbageDetector));
+        }
+
+        Ok(Self { filters })
+    }
+
+    pub fn load(path: &str) -> Result<Self, String> {
+        let json = fs::read_to_string(path).map_err(|e| format!("{}: {}", path, e))?;
+        Self::parse(&json)
+    }
+
+    pub fn filter_count(&self) -> usize {
+        self.filters.len()
+    }
+
+    pub fn check(&self, submission: &Submission<'_>) -> Result<(), ErrRes<'static>> {
+        if self.filters.is_empty() {
+            return Ok(());
+        }
+
+        let decoded;
+        let content = if submission.binary {
+            decoded = base64::decode(submission.content).unwrap_or_default();
+            Part::Opaque(&decoded)
+        } else if submission.encrypted {
+            Part::Opaque(submission.content.as_bytes())
+        } else {
+            Part::Text(submission.content)
+        };
+
+        let mut parts = vec![Part::Label(submission.title), content];
+        for file in submission.files {
+            parts.push(Part::Label(&file.name));
+            parts.push(if submission.encrypted {
+                Part::Opaque(file.content.as_bytes())
+            } else {
+                Part::Text(&file.content)
+            });
+        }
+
+        for part in &parts {
+            for filter in &self.filters {
+                filter.check(part)?;
+            }
+        }
+        Ok(())
+    }
+}
+
+fn parse_sha256(hex: &str) -> Option<[u8; 32]> {
+    let hex = hex.trim();
+    if hex.len() != 64 || !hex.is_ascii() {
+        return None;
+    }
+    let mut digest = [0; 32];
+    for (i, byte) in digest.iter_mut().enumerate() {
+        *byte = u8::from_str_radix(&hex[i * 2..i * 2 + 2], 16).ok()?;
+    }
+    Some(digest)
+}
+
+#[cfg(test)]
+#[test]
+fn test_policy() {
+    let policy = Policy::parse(
+        r#"{
+            "deny_patterns": ["(?i)cheap\\s+pills", "^https?://\\S+$"],
+            "banned_hashes": ["2CF24DBA5FB0A30E26E83B2AC5B9E29E1B161E5C1FA7425E73043362938B9824"],
+            "max_line_length": 10,
+            "reject_garbage": true
+        }"#,
+    )
+    .unwrap();
+    assert_eq!(policy.filter_count(), 4);
+
+    let check = |title, content| {
+        policy
+            .check(&Submission {
+                title,
+                content,
+                files: &[],
+                encrypted: false,
+                binary: false,
+            })
+            .map_err(|e| e.code)
+    };
+    assert_eq!(check("t", "ok\nfine"), Ok(()));
+    assert_eq!(check("CHEAP  Pills", "ok"), Err(1028));
+    assert_eq!(check("t", "hello"), Err(1029)); // sha256("hello")
+    assert_eq!(check("t", "0123456789\nééééééééééé"), Err(1030));
+    assert_eq!(check("t", "éééééééééé"), Ok(()));
+    assert_eq!(check("t", "a\0b"), Err(1031));
+    assert_eq!(check("t", "\x01\x02 text"), Err(1031));
+
+    // binary records are hashed as their bytes, never matched
+    let upload = Submission {
+        title: "a.bin",
+        content: "aGVsbG8=",
+        files: &[],
+        encrypted: false,
+        binary: true,
+    };
+    assert_eq!(policy.check(&upload).map_err(|e| e.code), Err(1029));
+
+    let files = [RecordFile {
+        name: "cheap pills.txt".into(),
+        lang: "plaintext".into(),
+        content: "ok".into(),
+    }];
+    let bundle = Submission {
+        title: "t",
+        content: "",
+        files: &files,
+        encrypted: false,
+        binary: false,
+    };
+    assert_eq!(policy.check(&bundle).map_err(|e| e.code), Err(1028));
+
+    assert!(Policy::default().check(&bundle).is_ok());
+    assert!(Policy::parse(r#"{"deny_patterns": ["("]}"#).is_err());
+    assert!(Policy::parse(r#"{"banned_hashes": ["abc"]}"#).is_err());
+    assert!(Policy::parse(r#"{"max_line": 1}"#).is_err());
+}
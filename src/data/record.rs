@@ -1,22 +1,281 @@
-use crate::mem_store::{LruValueSize, WithDeadTime};
-use crate::time::{NanoTime, SecTime};
+use crate::data::compress::base64_bytes;
+use crate::data::password::verify_password;
+use crate::mem_store::{LruValueSize, SharedPart, WithDeadTime};
+use crate::time::{nano_to_sec, sec_to_nano, NanoTime, SecTime};
 
-#[derive(Debug)]
+use std::sync::Arc;
+
+use serde::{Deserialize, Serialize};
+
+/// A named file of a bundle.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct RecordFile {
+    pub name: String,
+    pub lang: String,
+    pub content: String,
+}
+
+const MAX_FILE_NAME_LEN: usize = 128;
+
+impl RecordFile {
+    /// A name must be usable as a path segment and an archive entry.
+    pub fn is_valid_name(name: &str) -> bool {
+        !name.is_empty()
+            && name.len() <= MAX_FILE_NAME_LEN
+            && name != "."
+            && name != ".."
+            && !name
+                .chars()
+                .any(|c| c.is_control() || c == '/' || c == '\\' || c == '"')
+    }
+
+    #[inline]
+    fn text_size(&self) -> usize {
+        std::mem::size_of::<Self>() + self.name.len() + self.lang.len() + self.content.len()
+    }
+}
+
+/// The original file of a binary record, see `data::binary`.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Binary {
+    pub file_name: String,
+    pub content_type: String,
+    pub size: u64,
+}
+
+/// The content of a record moved out of it, see `data::compress`. Equal contents
+/// have equal bodies, sealed or not, and the memory store keeps them once.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Body {
+    Text(Arc<str>),
+    Zstd(#[serde(with = "base64_bytes::plain")] Arc<[u8]>),
+}
+
+impl AsRef<[u8]> for Body {
+    /// A zstd frame is never valid UTF-8, nor a sealed one base64 in practice,
+    /// so equal bytes are equal bodies.
+    #[inline]
+    fn as_ref(&self) -> &[u8] {
+        match self {
+            Body::Text(text) => text.as_bytes(),
+            Body::Zstd(zstd) => zstd,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Revision {
+    pub title: String,
+    pub lang: String,
+    pub content: String,
+    pub editing_time: SecTime,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub files: Vec<RecordFile>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct Record {
     pub title: String,
     pub lang: String,
     pub content: String,
     pub saving_time: SecTime,
+    #[serde(default)]
+    pub editing_time: SecTime, // 0 until `from_stored` fills it in
     pub expiration: SecTime,
     pub dead_time: NanoTime,
+    #[serde(default)]
+    pub delete_token: String, // empty never verifies
+    #[serde(default)]
+    pub edit_token: String,
+    #[serde(default)]
+    pub burn_after_reading: bool,
+    pub max_views: Option<u64>,
+    pub password_hash: Option<String>,
+    pub encryption_version: Option<u32>, // `None` for plaintext
+    pub sealed_key_id: Option<String>,   // `None` if not encrypted at rest
+    #[serde(default)]
+    pub revisions: Vec<Revision>, // prior revisions, oldest first
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub files: Vec<RecordFile>, // a bundle has files instead of content
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub binary: Option<Binary>, // content is the base64 of the bytes
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub parent_key: Option<String>, // the record this one is forked from
+    #[serde(default)]
+    pub fork_count: u64,
+    #[serde(
+        default,
+        with = "base64_bytes",
+        skip_serializing_if = "Option::is_none"
+    )]
+    pub packed: Option<Arc<[u8]>>, // compressed files and revisions, see `data::compress`
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub body: Option<Body>, // the content if moved out, see `data::compress`
+}
+
+/// The Redis value saved before records were stored whole.
+#[derive(Deserialize)]
+struct LegacyRecord {
+    title: String,
+    lang: String,
+    content: String,
+    expiration: SecTime,
+}
+
+impl Record {
+    /// Parses a stored record, filling in fields added since it was saved.
+    /// `nano` is its key, the saving time of a legacy value.
+    pub fn from_stored(nano: NanoTime, json: &str) -> serde_json::Result<Self> {
+        let mut record = match serde_json::from_str::<Record>(json) {
+            Ok(record) => record,
+            Err(err) => match serde_json::from_str::<LegacyRecord>(json) {
+                Err(_) => return Err(err),
+                Ok(legacy) => Record {
+                    title: legacy.title,
+                    lang: legacy.lang,
+                    content: legacy.content,
+                    saving_time: nano_to_sec(nano),
+                    editing_time: 0,
+                    expiration: legacy.expiration,
+                    dead_time: nano + sec_to_nano(legacy.expiration),
+                    delete_token: String::new(),
+                    edit_token: String::new(),
+                    burn_after_reading: false,
+                    max_views: None,
+                    password_hash: None,
+                    encryption_version: None,
+                    sealed_key_id: None,
+                    revisions: Vec::new(),
+                    files: Vec::new(),
+                    binary: None,
+                    parent_key: None,
+                    fork_count: 0,
+                    packed: None,
+                    body: None,
+                },
+            },
+        };
+        if record.editing_time == 0 {
+            record.editing_time = record.saving_time;
+        }
+        Ok(record)
+    }
+
+    /// The number of the current revision, starting from 0.
+    #[inline]
+    pub fn revision(&self) -> usize {
+        self.revisions.len()
+    }
+
+    /// The number of views allowed in total, `None` means unlimited.
+    #[inline]
+    pub fn view_limit(&self) -> Option<u64> {
+        if self.burn_after_reading {
+            Some(1)
+        } else {
+            self.max_views
+        }
+    }
+
+    /// Whether the record must be removed after being viewed `view_count` times.
+    #[inline]
+    pub fn is_exhausted(&self, view_count: u64) -> bool {
+        self.view_limit().is_some_and(|max| view_count >= max)
+    }
+
+    #[inline]
+    pub fn is_bundle(&self) -> bool {
+        !self.files.is_empty()
+    }
+
+    pub fn find_file(&self, name: &str) -> Option<&RecordFile> {
+        self.files.iter().find(|f| f.name == name)
+    }
+
+    #[inline]
+    pub fn is_binary(&self) -> bool {
+        self.binary.is_some()
+    }
+
+    #[inline]
+    pub fn is_encrypted(&self) -> bool {
+        self.encryption_version.is_some()
+    }
+
+    #[inline]
+    pub fn is_protected(&self) -> bool {
+        self.password_hash.is_some()
+    }
+
+    /// Always `true` for records without a password.
+    pub fn check_password(&self, password: Option<&str>) -> bool {
+        match (&self.password_hash, password) {
+            (None, _) => true,
+            (Some(hash), Some(password)) => verify_password(hash, password),
+            (Some(_), None) => false,
+        }
+    }
+
+    /// Replaces the current revision and keeps it in `revisions`.
+    pub fn edit(
+        &mut self,
+        title: String,
+        lang: String,
+        content: String,
+        files: Vec<RecordFile>,
+        editing_time: SecTime,
+    ) {
+        let prior = Revision {
+            title: std::mem::replace(&mut self.title, title),
+            lang: std::mem::replace(&mut self.lang, lang),
+            content: std::mem::replace(&mut self.content, content),
+            editing_time: std::mem::replace(&mut self.editing_time, editing_time),
+            files: std::mem::replace(&mut self.files, files),
+        };
+        self.revisions.push(prior);
+    }
 }
 
 impl LruValueSize for Record {
     fn lru_value_size(&self) -> usize {
+        // the body is shared, so counted in the pool instead
         std::mem::size_of::<Self>()
-            + self.title.as_bytes().len()
-            + self.lang.as_bytes().len()
-            + self.content.as_bytes().len()
+            + self.packed.as_ref().map_or(0, |packed| packed.len())
+            + self.title.len()
+            + self.lang.len()
+            + self.content.len()
+            + self.parent_key.as_ref().map_or(0, String::len)
+            + self
+                .binary
+                .as_ref()
+                .map_or(0, |b| b.file_name.len() + b.content_type.len())
+            + self.files.iter().map(RecordFile::text_size).sum::<usize>()
+            + self
+                .revisions
+                .iter()
+                .map(|r| {
+                    std::mem::size_of::<Revision>()
+                        + r.title.len()
+                        + r.lang.len()
+                        + r.content.len()
+                        + r.files.iter().map(RecordFile::text_size).sum::<usize>()
+                })
+                .sum::<usize>()
+    }
+}
+
+impl SharedPart for Record {
+    type Part = Body;
+
+    #[inline]
+    fn shared_part(&self) -> Option<&Body> {
+        self.body.as_ref()
+    }
+
+    #[inline]
+    fn set_shared_part(&mut self, part: Body) {
+        self.body = Some(part);
     }
 }
 
@@ -26,3 +285,125 @@ impl WithDeadTime for Record {
         self.dead_time
     }
 }
+
+#[cfg(test)]
+impl Record {
+    pub fn sample() -> Self {
+        Record {
+            title: "title".into(),
+            lang: "rust".into(),
+            content: "// content".into(),
+            saving_time: 1,
+            editing_time: 1,
+            expiration: 2,
+            dead_time: u128::from(u64::MAX) + 3,
+            delete_token: "token".into(),
+            edit_token: "token".into(),
+            burn_after_reading: false,
+            max_views: None,
+            password_hash: None,
+            encryption_version: None,
+            sealed_key_id: None,
+            revisions: Vec::new(),
+            files: Vec::new(),
+            binary: None,
+            parent_key: None,
+            fork_count: 0,
+            packed: None,
+            body: None,
+        }
+    }
+}
+
+#[cfg(test)]
+#[test]
+fn test_record_json() {
+    let mut record = Record::sample();
+    assert!(!record.is_exhausted(u64::MAX));
+    record.max_views = Some(3);
+    assert!(!record.is_exhausted(2));
+    assert!(record.is_exhausted(3));
+    record.burn_after_reading = true;
+    assert!(!record.is_exhausted(0));
+    assert!(record.is_exhausted(1));
+
+    assert!(record.check_password(None));
+    record.password_hash = Some(crate::data::password::hash_password("secret"));
+    assert!(record.is_protected());
+    assert!(!record.check_password(None));
+    assert!(!record.check_password(Some("wrong")));
+    assert!(record.check_password(Some("secret")));
+
+    let size = record.lru_value_size();
+    record.edit(
+        "title2".into(),
+        "rust".into(),
+        "// content2".into(),
+        vec![],
+        3,
+    );
+    assert_eq!(record.revision(), 1);
+    assert!(record.lru_value_size() > size + record.revisions[0].content.len());
+
+    let file = |name: &str| RecordFile {
+        name: name.into(),
+        lang: "rust".into(),
+        content: "fn main() {}".into(),
+    };
+    let size = record.lru_value_size();
+    record.files = vec![file("a.rs"), file("b.rs")];
+    assert!(record.is_bundle());
+    assert!(record.lru_value_size() >= size + 2 * (4 + 4 + 12));
+    assert_eq!(record.find_file("b.rs").unwrap().name, "b.rs");
+    assert!(record.find_file("c.rs").is_none());
+    for name in &["", ".", "..", "a/b", "a\\b", "a\"b", "a\nb"] {
+        assert!(!RecordFile::is_valid_name(name), "{:?}", name);
+    }
+    assert!(RecordFile::is_valid_name("Makefile"));
+
+    let size = record.lru_value_size();
+    record.binary = Some(Binary {
+        file_name: "a.png".into(),
+        content_type: "image/png".into(),
+        size: 0,
+    });
+    assert!(record.is_binary());
+    assert_eq!(record.lru_value_size(), size + 5 + 9);
+
+    record.parent_key = Some("KEY".into());
+    record.fork_count = 2;
+    let json_string = serde_json::to_string(&record).unwrap();
+    let record2: Record = serde_json::from_str(&json_string).unwrap();
+    assert_eq!(record2.dead_time, record.dead_time);
+    assert_eq!(record2.content, "// content2");
+    assert_eq!(record2.revisions[0].content, "// content");
+    assert_eq!(record2.revisions[0].editing_time, 1);
+    assert_eq!(record2.files[1].name, "b.rs");
+    assert_eq!(record2.binary.unwrap().content_type, "image/png");
+    assert_eq!(record2.parent_key.unwrap(), "KEY");
+    assert_eq!(record2.fork_count, 2);
+
+    // records saved before forks existed
+    let legacy = json_string
+        .replace(",\"parent_key\":\"KEY\"", "")
+        .replace(",\"fork_count\":2", "");
+    assert!(!legacy.contains("fork"));
+    let record3: Record = serde_json::from_str(&legacy).unwrap();
+    assert_eq!((record3.parent_key, record3.fork_count), (None, 0));
+    assert!(!json_string.contains("\"files\":[]"));
+
+    // records saved before edits and tokens existed
+    let old = r#"{"title":"t","lang":"rust","content":"c","saving_time":5,"expiration":60,"dead_time":7}"#;
+    let record4 = Record::from_stored(0, old).unwrap();
+    assert_eq!((record4.editing_time, record4.revision()), (5, 0));
+    assert!(record4.delete_token.is_empty() && !record4.burn_after_reading);
+
+    // values saved in Redis before records were stored whole
+    let nano = sec_to_nano(100);
+    let legacy = r#"{"title":"t","lang":"rust","content":"c","expiration":60}"#;
+    let record5 = Record::from_stored(nano, legacy).unwrap();
+    assert_eq!((record5.saving_time, record5.editing_time), (100, 100));
+    assert_eq!(record5.dead_time, sec_to_nano(160));
+    assert_eq!(record5.content, "c");
+    assert!(Record::from_stored(nano, r#"{"title":"t"}"#).is_err());
+}
@@ -0,0 +1,309 @@
+//! At-rest encryption of record content.
+//!
+//! Keys are configured as `id:base64key,...` with 32-byte keys. The first key
+//! seals new records and every key can open records sealed with it, so a key
+//! can be rotated by prepending a new one.
+//!
+//! A content body is sealed with a nonce derived from the key and its plaintext,
+//! so that equal contents stay equal and shareable, and bound to no record.
+
+use crate::data::record::{Body, Record, RecordFile};
+use crate::time::NanoTime;
+
+use std::collections::HashMap;
+use std::fmt::{self, Display};
+
+use aes_gcm::aead::{Aead, Payload};
+use aes_gcm::{Aes256Gcm, KeyInit, Nonce};
+use rand::Rng;
+use sha2::{Digest, Sha256};
+
+const KEY_LEN: usize = 32;
+const NONCE_LEN: usize = 12;
+const BODY_AAD: &[u8] = b"body";
+
+#[derive(Debug)]
+pub enum SealError {
+    UnknownKey(String),
+    Corrupted,
+}
+
+impl Display for SealError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            SealError::UnknownKey(id) => write!(f, "unknown key id: {}", id),
+            SealError::Corrupted => write!(f, "can not decrypt"),
+        }
+    }
+}
+
+struct Key {
+    cipher: Aes256Gcm,
+    secret: Vec<u8>, // derives the nonce of a body
+}
+
+pub struct Sealer {
+    current: String,
+    keys: HashMap<String, Key>,
+}
+
+impl Sealer {
+    pub fn parse(spec: &str) -> Result<Self, String> {
+        let mut current = None;
+        let mut keys = HashMap::new();
+        for item in spec.split(',').map(str::trim).filter(|s| !s.is_empty()) {
+            let mut it = item.splitn(2, ':');
+            let id = it.next().unwrap_or("");
+            let key = it
+                .next()
+                .and_then(|k| base64::decode(k).ok())
+                .filter(|k| k.len() == KEY_LEN)
+                .ok_or_else(|| format!("key {} must be {} bytes in base64", id, KEY_LEN))?;
+            if id.is_empty() || keys.contains_key(id) {
+                return Err(format!("bad or duplicate key id: {:?}", id));
+            }
+            // assert: key length is checked
+            let cipher = Aes256Gcm::new_from_slice(&key).unwrap();
+            keys.insert(
+                id.to_owned(),
+                Key {
+                    cipher,
+                    secret: key,
+                },
+            );
+            current.get_or_insert_with(|| id.to_owned());
+        }
+        let current = current.ok_or_else(|| String::from("no key"))?;
+        Ok(Self { current, keys })
+    }
+
+    #[inline]
+    pub fn current_key_id(&self) -> &str {
+        &self.current
+    }
+
+    #[inline]
+    pub fn key_count(&self) -> usize {
+        self.keys.len()
+    }
+
+    /// Encrypts title, content and files of every revision and the name of a binary file
+    /// with the current key, and the packed part and body if any. The record must not be sealed yet.
+    pub fn seal(&self, nano: NanoTime, record: &mut Record) {
+        let key = &self.keys[&self.current];
+        let encrypt = |nonce: [u8; NONCE_LEN], msg: &[u8], aad: &[u8]| -> Vec<u8> {
+            // assert: encryption of in-memory data never fails
+            let mut blob = key
+                .cipher
+                .encrypt(Nonce::from_slice(&nonce), Payload { msg, aad })
+                .unwrap();
+            blob.splice(0..0, nonce.iter().cloned());
+            blob
+        };
+        let seal_bytes = |msg: &[u8], name: &str| -> Vec<u8> {
+            encrypt(rand::thread_rng().gen(), msg, &aad(nano, name))
+        };
+        let seal = |field: &mut String, name: &str| {
+            *field = base64::encode(&seal_bytes(field.as_bytes(), name));
+        };
+        let seal_files = |files: &mut Vec<RecordFile>, prefix: &str| {
+            for (i, f) in files.iter_mut().enumerate() {
+                seal(&mut f.name, &format!("{}files/{}/name", prefix, i));
+                seal(&mut f.content, &format!("{}files/{}/content", prefix, i));
+            }
+        };
+        seal(&mut record.title, "title");
+        seal(&mut record.content, "content");
+        seal_files(&mut record.files, "");
+        if let Some(ref mut binary) = record.binary {
+            seal(&mut binary.file_name, "binary/file_name");
+        }
+        for (i, r) in record.revisions.iter_mut().enumerate() {
+            let prefix = format!("revisions/{}/", i);
+            seal(&mut r.title, &format!("{}title", prefix));
+            seal(&mut r.content, &format!("{}content", prefix));
+            seal_files(&mut r.files, &prefix);
+        }
+        if let Some(ref mut packed) = record.packed {
+            *packed = seal_bytes(packed, "packed").into();
+        }
+        if let Some(ref mut body) = record.body {
+            let hash = Sha256::new()
+                .chain_update(&key.secret)
+                .chain_update(body.as_ref())
+                .finalize();
+            let mut nonce = [0; NONCE_LEN];
+            nonce.copy_from_slice(&hash[..NONCE_LEN]);
+            let blob = encrypt(nonce, body.as_ref(), BODY_AAD);
+            *body = match body {
+                Body::Text(_) => Body::Text(base64::encode(&blob).into()),
+                Body::Zstd(_) => Body::Zstd(blob.into()),
+            };
+        }
+        record.sealed_key_id = Some(self.current.clone());
+    }
+
+    /// Decrypts a record sealed by `seal`. Does nothing to a plaintext record.
+    pub fn open(&self, nano: NanoTime, record: &mut Record) -> Result<(), SealError> {
+        let key_id = match record.sealed_key_id {
+            None => return Ok(()),
+            Some(ref key_id) => key_id,
+        };
+        let key = self
+            .keys
+            .get(key_id)
+            .ok_or_else(|| SealError::UnknownKey(key_id.clone()))?;
+        let decrypt = |blob: &[u8], aad: &[u8]| -> Result<Vec<u8>, SealError> {
+            if blob.len() < NONCE_LEN {
+                return Err(SealError::Corrupted);
+            }
+            let (nonce, msg) = blob.split_at(NONCE_LEN);
+            key.cipher
+                .decrypt(Nonce::from_slice(nonce), Payload { msg, aad })
+                .map_err(|_| SealError::Corrupted)
+        };
+        let open_bytes = |blob: &[u8], name: &str| decrypt(blob, &aad(nano, name));
+        let open = |field: &mut String, name: &str| -> Result<(), SealError> {
+            let blob = base64::decode(field.as_str()).map_err(|_| SealError::Corrupted)?;
+            let plain = open_bytes(&blob, name)?;
+            *field = String::from_utf8(plain).map_err(|_| SealError::Corrupted)?;
+            Ok(())
+        };
+        let open_files = |files: &mut Vec<RecordFile>, prefix: &str| -> Result<(), SealError> {
+            for (i, f) in files.iter_mut().enumerate() {
+                open(&mut f.name, &format!("{}files/{}/name", prefix, i))?;
+                open(&mut f.content, &format!("{}files/{}/content", prefix, i))?;
+            }
+            Ok(())
+        };
+        open(&mut record.title, "title")?;
+        open(&mut record.content, "content")?;
+        open_files(&mut record.files, "")?;
+        if let Some(ref mut binary) = record.binary {
+            open(&mut binary.file_name, "binary/file_name")?;
+        }
+        for (i, r) in record.revisions.iter_mut().enumerate() {
+            let prefix = format!("revisions/{}/", i);
+            open(&mut r.title, &format!("{}title", prefix))?;
+            open(&mut r.content, &format!("{}content", prefix))?;
+            open_files(&mut r.files, &prefix)?;
+        }
+        if let Some(ref mut packed) = record.packed {
+            *packed = open_bytes(packed, "packed")?.into();
+        }
+        if let Some(ref mut body) = record.body {
+            *body = match body {
+                Body::Text(text) => {
+                    let blob = base64::decode(text.as_bytes()).map_err(|_| SealError::Corrupted)?;
+                    let plain = decrypt(&blob, BODY_AAD)?;
+                    let text = String::from_utf8(plain).map_err(|_| SealError::Corrupted)?;
+                    Body::Text(text.into())
+                }
+                Body::Zstd(blob) => Body::Zstd(decrypt(blob, BODY_AAD)?.into()),
+            };
+        }
+        record.sealed_key_id = None;
+        Ok(())
+    }
+}
+
+/// Binds a ciphertext to its record and field. `name` is the path of the field,
+/// with the index of its file and revision, so that no two fields share one.
+fn aad(nano: NanoTime, name: &str) -> Vec<u8> {
+    let mut aad = nano.to_be_bytes().to_vec();
+    aad.extend_from_slice(name.as_bytes());
+    aad
+}
+
+#[cfg(test)]
+#[test]
+fn test_seal() {
+    use crate::data::record::Binary;
+    use crate::mem_store::LruValueSize;
+
+    let k1 = base64::encode(&[1; KEY_LEN]);
+    let k2 = base64::encode(&[2; KEY_LEN]);
+    let old = Sealer::parse(&format!("a:{}", k1)).unwrap();
+    let new = Sealer::parse(&format!("b:{}, a:{}", k2, k1)).unwrap();
+    assert_eq!(new.current_key_id(), "b");
+    assert_eq!(new.key_count(), 2);
+    assert!(Sealer::parse("a:short").is_err());
+    assert!(Sealer::parse(&format!("a:{},a:{}", k1, k2)).is_err());
+    assert!(Sealer::parse("").is_err());
+
+    let mut record = Record::sample();
+    let file = RecordFile {
+        name: "a.rs".into(),
+        lang: "rust".into(),
+        content: "// a".into(),
+    };
+    record.edit("title2".into(), "rust".into(), "".into(), vec![file], 2);
+    record.packed = Some(vec![1, 2, 3].into());
+    record.body = Some(Body::Text("shared".into()));
+    let size = record.lru_value_size();
+    record.binary = Some(Binary {
+        file_name: "a.bin".into(),
+        content_type: "image/png".into(),
+        size: 0,
+    });
+
+    // plaintext is left as is
+    new.open(7, &mut record).unwrap();
+
+    old.seal(7, &mut record);
+    assert_ne!(record.revisions[0].content, "// content");
+    assert_ne!(record.files[0].name, "a.rs");
+    assert_ne!(record.binary.as_ref().unwrap().file_name, "a.bin");
+    assert_eq!(record.sealed_key_id.as_ref().unwrap(), "a");
+    let packed_len = record.packed.as_ref().unwrap().len();
+    assert!(record.lru_value_size() > size + packed_len - 3);
+
+    // equal bodies stay equal in any record, but not under another key
+    let body = record.body.clone().unwrap();
+    assert_ne!(body.as_ref(), b"shared");
+    let mut other = Record::sample();
+    other.body = Some(Body::Text("shared".into()));
+    old.seal(8, &mut other);
+    assert_eq!(other.body.as_ref().unwrap().as_ref(), body.as_ref());
+    let mut other = Record::sample();
+    other.body = Some(Body::Text("shared".into()));
+    new.seal(8, &mut other);
+    assert_ne!(other.body.as_ref().unwrap().as_ref(), body.as_ref());
+    let mut zstd = Record::sample();
+    zstd.body = Some(Body::Zstd(vec![4, 5, 6].into()));
+    old.seal(9, &mut zstd);
+    new.open(9, &mut zstd).unwrap();
+    assert_eq!(zstd.body.unwrap().as_ref(), &[4, 5, 6]);
+
+    // wrong record
+    assert!(new.open(8, &mut record.clone()).is_err());
+
+    // swapped fields, revisions or files
+    let mut swapped = record.clone();
+    swapped.title = swapped.revisions[0].title.clone();
+    assert!(new.open(7, &mut swapped).is_err());
+    let mut swapped = record.clone();
+    swapped.files.push(swapped.files[0].clone());
+    assert!(new.open(7, &mut swapped).is_err());
+    let mut swapped = record.clone();
+    swapped.body = Some(Body::Text(swapped.content.clone().into()));
+    assert!(new.open(7, &mut swapped).is_err());
+
+    // rotated
+    new.open(7, &mut record).unwrap();
+    assert_eq!(record.title, "title2");
+    assert_eq!(record.content, "");
+    assert_eq!(record.files[0].name, "a.rs");
+    assert_eq!(record.files[0].content, "// a");
+    assert_eq!(record.binary.as_ref().unwrap().file_name, "a.bin");
+    assert_eq!(record.revisions[0].content, "// content");
+    assert_eq!(&record.packed.as_ref().unwrap()[..], &[1, 2, 3]);
+    assert_eq!(record.body.as_ref().unwrap().as_ref(), b"shared");
+    assert_eq!(record.sealed_key_id, None);
+
+    new.seal(7, &mut record);
+    assert!(matches!(
+        old.open(7, &mut record),
+        Err(SealError::UnknownKey(_))
+    ));
+}
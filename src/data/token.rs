@@ -0,0 +1,34 @@
+use rand::distributions::Alphanumeric;
+use rand::Rng;
+
+const TOKEN_LEN: usize = 24;
+
+pub fn gen_token() -> String {
+    rand::thread_rng()
+        .sample_iter(&Alphanumeric)
+        .take(TOKEN_LEN)
+        .collect()
+}
+
+/// Compares in constant time. An empty token never matches.
+pub fn verify_token(expected: &str, given: &str) -> bool {
+    if expected.is_empty() || expected.len() != given.len() {
+        return false;
+    }
+    expected
+        .bytes()
+        .zip(given.bytes())
+        .fold(0, |acc, (a, b)| acc | (a ^ b))
+        == 0
+}
+
+#[cfg(test)]
+#[test]
+fn test_token() {
+    let token = gen_token();
+    assert_eq!(token.len(), TOKEN_LEN);
+    assert!(verify_token(&token, &token.clone()));
+    assert!(!verify_token(&token, &gen_token()));
+    assert!(!verify_token(&token, ""));
+    assert!(!verify_token("", ""));
+}
@@ -2,54 +2,135 @@
 //!
 //! MAX_POST_SIZE: 32 KB
 //!
+//! MAX_UPLOAD_SIZE: 10 MB
+//!
 //! MAX_EXPIRATION: 7 days
 //!
 //! CLEAN_DURATION: 5000 ms
 //!
+//! MAX_REVISIONS: 16
+//!
+//! MAX_FILES: 32
+//!
 //! ADDR: "localhost:8088"
 //!
 //! CRYPT_KEY: "magic"
 //!
 //! REDIS_URL: None
+//!
+//! SQLITE_PATH: None
+//!
+//! FS_DIR: None
+//!
+//! AT_REST_KEYS: None
+//!
+//! COMPRESSION_LEVEL: 3
+//!
+//! SNAPSHOT_PATH: None
+//!
+//! SNAPSHOT_DURATION: 60000 ms
+//!
+//! WAL_PATH: None
+//!
+//! WAL_FSYNC: "periodic"
+//!
+//! RATE_LIMIT_WINDOW: 60 s
+//!
+//! RATE_LIMIT_SAVES: 0 (unlimited)
+//!
+//! RATE_LIMIT_SAVE_BYTES: 0 (unlimited)
+//!
+//! RATE_LIMIT_READS: 0 (unlimited)
+//!
+//! TRUST_PROXY: false
+//!
+//! POLICY_PATH: None
 
+use crate::mem_store::Fsync;
 use crate::time::SecTime;
 
 use std::env;
 use std::str::FromStr;
 
-fn parse<T: FromStr>(var: &'static str, default: T) -> T {
+fn parse<T: FromStr>(var: &str, default: T) -> T {
     env::var(var)
         .ok()
         .and_then(|s| s.parse::<T>().ok())
         .unwrap_or(default)
 }
 
-const DEFAULT_ADDR: &'static str = "localhost:8088";
-const DEFAULT_CRYPT_KEY: &'static str = "magic";
+const DEFAULT_ADDR: &str = "localhost:8088";
+const DEFAULT_CRYPT_KEY: &str = "magic";
 
 lazy_static! {
-    pub static ref MAX_STORE_SIZE: usize = { parse("PASTEBIN_MAX_STORE_SIZE", 100 * 1024 * 1024) };
-    pub static ref MAX_POST_SIZE: usize = { parse("PASTEBIN_MAX_POST_SIZE", 32 * 1024) };
-    pub static ref MAX_EXPIRATION: SecTime = { parse("PASTEBIN_MAX_EXPIRATION", 7 * 24 * 60 * 60) };
-    pub static ref CLEAN_DURATION: u64 = { parse("PASTEBIN_CLEAN_DURATION", 5000) };
-    pub static ref ADDR: String = { env::var("PASTEBIN_ADDR").unwrap_or(DEFAULT_ADDR.into()) };
+    pub static ref MAX_STORE_SIZE: usize = parse("PASTEBIN_MAX_STORE_SIZE", 100 * 1024 * 1024);
+    pub static ref MAX_POST_SIZE: usize = parse("PASTEBIN_MAX_POST_SIZE", 32 * 1024);
+    pub static ref MAX_UPLOAD_SIZE: usize = parse("PASTEBIN_MAX_UPLOAD_SIZE", 10 * 1024 * 1024);
+    pub static ref MAX_EXPIRATION: SecTime = parse("PASTEBIN_MAX_EXPIRATION", 7 * 24 * 60 * 60);
+    pub static ref CLEAN_DURATION: u64 = parse("PASTEBIN_CLEAN_DURATION", 5000);
+    pub static ref MAX_REVISIONS: usize = parse("PASTEBIN_MAX_REVISIONS", 16);
+    pub static ref MAX_FILES: usize = parse("PASTEBIN_MAX_FILES", 32);
+    pub static ref ADDR: String = env::var("PASTEBIN_ADDR").unwrap_or(DEFAULT_ADDR.into());
     pub static ref CRYPT_KEY: String =
-        { env::var("PASTEBIN_CRYPT_KEY").unwrap_or(DEFAULT_CRYPT_KEY.into()) };
-    pub static ref REDIS_URL: Option<String> = { env::var("PASTEBIN_REDIS_URL").ok() };
+        env::var("PASTEBIN_CRYPT_KEY").unwrap_or(DEFAULT_CRYPT_KEY.into());
+    pub static ref REDIS_URL: Option<String> = env::var("PASTEBIN_REDIS_URL").ok();
+    pub static ref SQLITE_PATH: Option<String> = env::var("PASTEBIN_SQLITE_PATH").ok();
+    pub static ref FS_DIR: Option<String> = env::var("PASTEBIN_FS_DIR").ok();
+    pub static ref SNAPSHOT_PATH: Option<String> = env::var("PASTEBIN_SNAPSHOT_PATH").ok();
+    pub static ref SNAPSHOT_DURATION: u64 = parse("PASTEBIN_SNAPSHOT_DURATION", 60 * 1000);
+    pub static ref WAL_PATH: Option<String> = env::var("PASTEBIN_WAL_PATH").ok();
+    pub static ref WAL_FSYNC: Fsync = parse("PASTEBIN_WAL_FSYNC", Fsync::Periodic);
+    pub static ref AT_REST_KEYS: Option<String> = env::var("PASTEBIN_AT_REST_KEYS").ok();
+    pub static ref COMPRESSION_LEVEL: i32 = parse("PASTEBIN_COMPRESSION_LEVEL", 3);
+    pub static ref RATE_LIMIT_WINDOW: SecTime = parse("PASTEBIN_RATE_LIMIT_WINDOW", 60);
+    pub static ref RATE_LIMIT_SAVES: u64 = parse("PASTEBIN_RATE_LIMIT_SAVES", 0);
+    pub static ref RATE_LIMIT_SAVE_BYTES: u64 = parse("PASTEBIN_RATE_LIMIT_SAVE_BYTES", 0);
+    pub static ref RATE_LIMIT_READS: u64 = parse("PASTEBIN_RATE_LIMIT_READS", 0);
+    pub static ref TRUST_PROXY: bool = parse("PASTEBIN_TRUST_PROXY", false);
+    pub static ref POLICY_PATH: Option<String> = env::var("PASTEBIN_POLICY_PATH").ok();
 }
 
 pub fn info_env() {
     info!("ADDR: {}", *ADDR);
     info!("MAX_POST_SIZE: {} bytes", *MAX_POST_SIZE);
+    info!("MAX_UPLOAD_SIZE: {} bytes", *MAX_UPLOAD_SIZE);
+    info!("MAX_REVISIONS: {}", *MAX_REVISIONS);
+    info!("MAX_FILES: {}", *MAX_FILES);
+    if *RATE_LIMIT_SAVES != 0 || *RATE_LIMIT_SAVE_BYTES != 0 || *RATE_LIMIT_READS != 0 {
+        info!(
+            "RATE_LIMIT per {} s: saves: {}, save_bytes: {}, reads: {}, TRUST_PROXY: {}",
+            *RATE_LIMIT_WINDOW,
+            *RATE_LIMIT_SAVES,
+            *RATE_LIMIT_SAVE_BYTES,
+            *RATE_LIMIT_READS,
+            *TRUST_PROXY
+        );
+    }
     // info!("CRYPT_KEY: {}", *CRYPT_KEY);
-    match *REDIS_URL {
-        Some(ref redis_url) => {
+    match (&*REDIS_URL, &*SQLITE_PATH, &*FS_DIR) {
+        (Some(redis_url), _, _) => {
             info!("REDIS_URL: {}", redis_url);
         }
-        None => {
+        (None, Some(sqlite_path), _) => {
+            info!("SQLITE_PATH: {}", sqlite_path);
+            info!("CLEAN_DURATION: {} ms", *CLEAN_DURATION);
+        }
+        (None, None, Some(fs_dir)) => {
+            info!("FS_DIR: {}", fs_dir);
+            info!("CLEAN_DURATION: {} ms", *CLEAN_DURATION);
+        }
+        (None, None, None) => {
             info!("MAX_STORE_SIZE: {} bytes", *MAX_STORE_SIZE);
             info!("MAX_EXPIRATION: {} s", *MAX_EXPIRATION);
             info!("CLEAN_DURATION: {} ms", *CLEAN_DURATION);
+            if let Some(ref snapshot_path) = *SNAPSHOT_PATH {
+                info!("SNAPSHOT_PATH: {}", snapshot_path);
+                info!("SNAPSHOT_DURATION: {} ms", *SNAPSHOT_DURATION);
+            }
+            if let Some(ref wal_path) = *WAL_PATH {
+                info!("WAL_PATH: {}", wal_path);
+                info!("WAL_FSYNC: {:?}", *WAL_FSYNC);
+            }
         }
     }
 }
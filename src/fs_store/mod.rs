@@ -0,0 +1,73 @@
+mod state;
+mod store;
+
+pub use self::state::State;
+
+use self::store::FsStore;
+use crate::env::{CLEAN_DURATION, FS_DIR};
+use crate::time::{now_nano, NanoTime};
+
+use std::io;
+use std::sync::Mutex;
+use std::thread;
+use std::time::Duration;
+
+lazy_static! {
+    // serializes read-modify-write of records across workers
+    static ref LOCK: Mutex<()> = Mutex::new(());
+}
+
+fn is_dead(store: &FsStore, key: &str, now: NanoTime) -> bool {
+    match store.dead_time(key) {
+        Ok(dead_time) => dead_time.is_some_and(|dead_time| dead_time <= now),
+        Err(err) => {
+            warn!("FS: skip {}: {}", key, err);
+            false
+        }
+    }
+}
+
+/// Reads sidecars without the lock, which is only taken to remove a dead record.
+fn clean(store: &FsStore, now: NanoTime) -> io::Result<usize> {
+    let mut removed_count = 0;
+    for key in store.keys()? {
+        if !is_dead(store, &key, now) {
+            continue;
+        }
+        // assert: LOCK.lock never returns Err or paincs
+        let _guard = LOCK.lock().unwrap();
+        // checked again under the lock, a view may have removed it meanwhile
+        if is_dead(store, &key, now) && store.remove(&key)? {
+            removed_count += 1;
+        }
+    }
+    Ok(removed_count)
+}
+
+fn start_clean(store: FsStore) {
+    thread::spawn(move || loop {
+        let now = now_nano();
+        match clean(&store, now) {
+            Ok(0) => {}
+            Ok(removed_count) => info!(
+                "CLEAN removed: {}, in {} ns",
+                removed_count,
+                now_nano() - now
+            ),
+            Err(err) => error!("FS: {}", err),
+        }
+
+        thread::sleep(Duration::from_millis(*CLEAN_DURATION));
+    });
+}
+
+/// Creates the directory and starts the expiry sweep. Called once.
+pub fn start() {
+    let store = FsStore::open(FS_DIR.as_ref().unwrap()).expect("Can not open store directory");
+    start_clean(store);
+}
+
+/// Opens the store for a worker.
+pub fn init() -> State {
+    State::new(FS_DIR.as_ref().unwrap())
+}
@@ -0,0 +1,96 @@
+use super::store::FsStore;
+use super::LOCK;
+use crate::data::key::nano_to_key;
+use crate::data::record::Record;
+use crate::shared::store::{Access, RecordStore, StoreHealth, StoreResult, StoreStats};
+use crate::time::{now_nano, NanoTime};
+
+use std::io;
+
+pub struct State {
+    pub store: FsStore,
+}
+
+impl State {
+    pub fn new(dir: &str) -> Self {
+        let store = FsStore::open(dir).expect("Can not open store directory");
+        Self { store }
+    }
+
+    /// Loads a record which is still alive.
+    fn load(&self, key: &str) -> io::Result<Option<Record>> {
+        let record = self.store.load(key)?;
+        Ok(record.filter(|record| record.dead_time > now_nano()))
+    }
+}
+
+// assert: LOCK.lock never returns Err or paincs
+impl RecordStore for State {
+    fn save(&self, nano: NanoTime, record: Record) -> StoreResult<()> {
+        let key = nano_to_key(nano);
+        let _guard = LOCK.lock().unwrap();
+        Ok(self.store.save(&key, &record)?)
+    }
+
+    fn access<G: FnOnce(&Record) -> bool>(
+        &self,
+        nano: NanoTime,
+        guard: G,
+    ) -> StoreResult<Option<Access>> {
+        let key = nano_to_key(nano);
+        let _guard = LOCK.lock().unwrap();
+
+        let record = match self.load(&key)? {
+            None => return Ok(None),
+            Some(record) => record,
+        };
+        if record.is_protected() && !guard(&record) {
+            return Ok(Some(Access::Denied));
+        }
+
+        let views = self.store.views(&key)? + 1;
+        if record.is_exhausted(views) {
+            self.store.remove(&key)?;
+        } else {
+            self.store.set_views(&key, views)?;
+        }
+        Ok(Some(Access::Found(record, views)))
+    }
+
+    fn peek(&self, nano: NanoTime) -> StoreResult<Option<Record>> {
+        let key = nano_to_key(nano);
+        let _guard = LOCK.lock().unwrap();
+        Ok(self.load(&key)?)
+    }
+
+    fn update<F: FnMut(&mut Record)>(&self, nano: NanoTime, mut f: F) -> StoreResult<bool> {
+        let key = nano_to_key(nano);
+        let _guard = LOCK.lock().unwrap();
+        let mut record = match self.load(&key)? {
+            None => return Ok(false),
+            Some(record) => record,
+        };
+        f(&mut record);
+        self.store.save(&key, &record)?;
+        Ok(true)
+    }
+
+    fn delete(&self, nano: NanoTime) -> StoreResult<bool> {
+        let key = nano_to_key(nano);
+        let _guard = LOCK.lock().unwrap();
+        Ok(self.store.remove(&key)?)
+    }
+
+    fn stats(&self) -> Option<StoreStats> {
+        None
+    }
+
+    fn health(&self) -> StoreResult<StoreHealth> {
+        self.store.check()?;
+        Ok(StoreHealth {
+            stats: None,
+            max_store_size: None,
+            gc_running: None,
+        })
+    }
+}
@@ -0,0 +1,277 @@
+use crate::data::record::{Binary, Record, RecordFile, Revision};
+use crate::time::{NanoTime, SecTime};
+
+use std::fs::{self, File};
+use std::io::{self, Write};
+use std::path::PathBuf;
+
+use rand::Rng;
+use serde::de::DeserializeOwned;
+use serde::{Deserialize, Serialize};
+
+// <key>.json is the sidecar, the other files hold the rest of the record
+// the sidecar is written last and removed first, so it marks a complete record
+const META_EXT: &str = "json";
+const CONTENT_EXT: &str = "txt";
+const FILES_EXT: &str = "files";
+const REVISIONS_EXT: &str = "revisions";
+const SECRETS_EXT: &str = "secrets";
+const VIEWS_EXT: &str = "views";
+
+/// What ops grep for and the sweep reads, without tokens or bulky contents.
+#[derive(Serialize, Deserialize)]
+struct Meta {
+    title: String,
+    lang: String,
+    saving_time: SecTime,
+    editing_time: SecTime,
+    expiration: SecTime,
+    dead_time: NanoTime,
+    burn_after_reading: bool,
+    max_views: Option<u64>,
+    encryption_version: Option<u32>,
+    sealed_key_id: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    binary: Option<Binary>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    parent_key: Option<String>,
+    fork_count: u64,
+}
+
+#[derive(Serialize, Deserialize)]
+struct Secrets {
+    delete_token: String,
+    edit_token: String,
+    password_hash: Option<String>,
+}
+
+pub struct FsStore {
+    dir: PathBuf,
+}
+
+fn read_optional(path: &PathBuf) -> io::Result<Option<String>> {
+    match fs::read_to_string(path) {
+        Ok(s) => Ok(Some(s)),
+        Err(ref e) if e.kind() == io::ErrorKind::NotFound => Ok(None),
+        Err(e) => Err(e),
+    }
+}
+
+fn remove_optional(path: &PathBuf) -> io::Result<bool> {
+    match fs::remove_file(path) {
+        Ok(()) => Ok(true),
+        Err(ref e) if e.kind() == io::ErrorKind::NotFound => Ok(false),
+        Err(e) => Err(e),
+    }
+}
+
+fn invalid_data(err: impl ToString) -> io::Error {
+    io::Error::new(io::ErrorKind::InvalidData, err.to_string())
+}
+
+fn read_json<T: DeserializeOwned>(path: &PathBuf) -> io::Result<T> {
+    serde_json::from_str(&fs::read_to_string(path)?).map_err(invalid_data)
+}
+
+impl FsStore {
+    pub fn open(dir: &str) -> io::Result<Self> {
+        fs::create_dir_all(dir)?;
+        Ok(Self { dir: dir.into() })
+    }
+
+    fn path(&self, key: &str, ext: &str) -> PathBuf {
+        self.dir.join(format!("{}.{}", key, ext))
+    }
+
+    /// Writes through a temporary file in the same directory, then renames it.
+    fn write_atomic(&self, key: &str, ext: &str, data: &[u8]) -> io::Result<()> {
+        let tmp_path = self.path(
+            key,
+            &format!("{}.{}.tmp", ext, rand::thread_rng().gen::<u32>()),
+        );
+        let mut file = File::create(&tmp_path)?;
+        file.write_all(data)?;
+        file.sync_all()?;
+        fs::rename(&tmp_path, self.path(key, ext))
+    }
+
+    fn write_json(&self, key: &str, ext: &str, value: &impl Serialize) -> io::Result<()> {
+        // assert: the parts of a Record are valid
+        let json = serde_json::to_vec_pretty(value).unwrap();
+        self.write_atomic(key, ext, &json)
+    }
+
+    /// The file store is not compressed, so `record.packed` and `record.body` are never set.
+    pub fn save(&self, key: &str, record: &Record) -> io::Result<()> {
+        self.write_atomic(key, CONTENT_EXT, record.content.as_bytes())?;
+        self.write_json(key, FILES_EXT, &record.files)?;
+        self.write_json(key, REVISIONS_EXT, &record.revisions)?;
+        let secrets = Secrets {
+            delete_token: record.delete_token.clone(),
+            edit_token: record.edit_token.clone(),
+            password_hash: record.password_hash.clone(),
+        };
+        self.write_json(key, SECRETS_EXT, &secrets)?;
+        let meta = Meta {
+            title: record.title.clone(),
+            lang: record.lang.clone(),
+            saving_time: record.saving_time,
+            editing_time: record.editing_time,
+            expiration: record.expiration,
+            dead_time: record.dead_time,
+            burn_after_reading: record.burn_after_reading,
+            max_views: record.max_views,
+            encryption_version: record.encryption_version,
+            sealed_key_id: record.sealed_key_id.clone(),
+            binary: record.binary.clone(),
+            parent_key: record.parent_key.clone(),
+            fork_count: record.fork_count,
+        };
+        self.write_json(key, META_EXT, &meta)
+    }
+
+    pub fn load(&self, key: &str) -> io::Result<Option<Record>> {
+        let json = match read_optional(&self.path(key, META_EXT))? {
+            None => return Ok(None),
+            Some(json) => json,
+        };
+        let meta: Meta = serde_json::from_str(&json).map_err(invalid_data)?;
+        let secrets: Secrets = read_json(&self.path(key, SECRETS_EXT))?;
+        let files: Vec<RecordFile> = read_json(&self.path(key, FILES_EXT))?;
+        let revisions: Vec<Revision> = read_json(&self.path(key, REVISIONS_EXT))?;
+        Ok(Some(Record {
+            title: meta.title,
+            lang: meta.lang,
+            content: fs::read_to_string(self.path(key, CONTENT_EXT))?,
+            saving_time: meta.saving_time,
+            editing_time: meta.editing_time,
+            expiration: meta.expiration,
+            dead_time: meta.dead_time,
+            delete_token: secrets.delete_token,
+            edit_token: secrets.edit_token,
+            burn_after_reading: meta.burn_after_reading,
+            max_views: meta.max_views,
+            password_hash: secrets.password_hash,
+            encryption_version: meta.encryption_version,
+            sealed_key_id: meta.sealed_key_id,
+            revisions,
+            files,
+            binary: meta.binary,
+            parent_key: meta.parent_key,
+            fork_count: meta.fork_count,
+            packed: None,
+            body: None,
+        }))
+    }
+
+    /// Reads only the sidecar. `None` if the record is gone.
+    pub fn dead_time(&self, key: &str) -> io::Result<Option<NanoTime>> {
+        let json = match read_optional(&self.path(key, META_EXT))? {
+            None => return Ok(None),
+            Some(json) => json,
+        };
+        let meta: Meta = serde_json::from_str(&json).map_err(invalid_data)?;
+        Ok(Some(meta.dead_time))
+    }
+
+    pub fn views(&self, key: &str) -> io::Result<u64> {
+        match read_optional(&self.path(key, VIEWS_EXT))? {
+            None => Ok(0),
+            Some(s) => s.trim().parse().map_err(invalid_data),
+        }
+    }
+
+    pub fn set_views(&self, key: &str, views: u64) -> io::Result<()> {
+        self.write_atomic(key, VIEWS_EXT, views.to_string().as_bytes())
+    }
+
+    pub fn remove(&self, key: &str) -> io::Result<bool> {
+        let found = remove_optional(&self.path(key, META_EXT))?;
+        for ext in &[
+            CONTENT_EXT,
+            FILES_EXT,
+            REVISIONS_EXT,
+            SECRETS_EXT,
+            VIEWS_EXT,
+        ] {
+            remove_optional(&self.path(key, ext))?;
+        }
+        Ok(found)
+    }
+
+    /// Checks that the directory is still readable.
+    pub fn check(&self) -> io::Result<()> {
+        fs::read_dir(&self.dir).map(drop)
+    }
+
+    /// Lists keys of complete records.
+    pub fn keys(&self) -> io::Result<Vec<String>> {
+        let mut keys = Vec::new();
+        for entry in fs::read_dir(&self.dir)? {
+            let path = entry?.path();
+            if path.extension().and_then(|e| e.to_str()) != Some(META_EXT) {
+                continue;
+            }
+            if let Some(key) = path.file_stem().and_then(|s| s.to_str()) {
+                keys.push(key.to_owned());
+            }
+        }
+        Ok(keys)
+    }
+}
+
+#[cfg(test)]
+#[test]
+fn test_fs_store() {
+    use crate::time::now_nano;
+
+    let dir = std::env::temp_dir().join(format!("pastebin-fs-{}", now_nano()));
+    let store = FsStore::open(dir.to_str().unwrap()).unwrap();
+
+    let mut record = Record::sample();
+    record.dead_time = 100;
+    store.save("A", &record).unwrap();
+    record.dead_time = 200;
+    record.password_hash = Some("hash".into());
+    record.edit(
+        "title2".into(),
+        "rust".into(),
+        "// content2".into(),
+        Vec::new(),
+        2,
+    );
+    store.save("B", &record).unwrap();
+    assert_eq!(fs::read_to_string(dir.join("A.txt")).unwrap(), "// content");
+
+    // the sidecar has neither secrets nor revisions
+    let sidecar = fs::read_to_string(dir.join("B.json")).unwrap();
+    assert!(sidecar.contains("\"title2\""));
+    assert!(!sidecar.contains("token") && !sidecar.contains("hash"));
+    assert!(!sidecar.contains("// content"));
+
+    let loaded = store.load("B").unwrap().unwrap();
+    assert_eq!(loaded.content, record.content);
+    assert_eq!(loaded.dead_time, 200);
+    assert_eq!(loaded.edit_token, "token");
+    assert_eq!(loaded.password_hash.as_deref(), Some("hash"));
+    assert_eq!(loaded.revisions[0].content, "// content");
+    assert_eq!(store.dead_time("B").unwrap(), Some(200));
+    assert_eq!(store.dead_time("C").unwrap(), None);
+    assert!(store.load("C").unwrap().is_none());
+
+    assert_eq!(store.views("A").unwrap(), 0);
+    store.set_views("A", 3).unwrap();
+    assert_eq!(store.views("A").unwrap(), 3);
+
+    let mut keys = store.keys().unwrap();
+    keys.sort();
+    assert_eq!(keys, vec!["A", "B"]);
+    assert!(store.remove("A").unwrap());
+    assert!(store.load("A").unwrap().is_none());
+    assert!(!dir.join("A.views").exists());
+    assert!(store.remove("B").unwrap());
+    assert!(!store.remove("B").unwrap());
+    assert_eq!(fs::read_dir(&dir).unwrap().count(), 0);
+
+    fs::remove_dir(&dir).unwrap();
+}
@@ -0,0 +1,29 @@
+mod delete_record;
+mod edit_record;
+mod find_archive;
+mod find_diff;
+mod find_file;
+mod find_html;
+mod find_raw;
+mod find_record;
+mod find_revision;
+mod fork_record;
+mod health;
+mod metrics;
+mod save_record;
+mod upload_record;
+
+pub use self::delete_record::delete_record;
+pub use self::edit_record::edit_record;
+pub use self::find_archive::find_archive;
+pub use self::find_diff::find_diff;
+pub use self::find_file::find_file;
+pub use self::find_html::find_html;
+pub use self::find_raw::find_raw;
+pub use self::find_record::find_record;
+pub use self::find_revision::find_revision;
+pub use self::fork_record::fork_record;
+pub use self::health::{healthz, readyz};
+pub use self::metrics::metrics;
+pub use self::save_record::save_record;
+pub use self::upload_record::upload_record;
@@ -0,0 +1,41 @@
+use crate::data::dto::ErrRes;
+use crate::data::key::key_to_nano;
+use crate::data::token::verify_token;
+use crate::shared::error::HandlerError;
+use crate::shared::resource::DELETE_TOKEN_HEADER;
+use crate::shared::store::RecordStore;
+
+use actix_web::{web, HttpRequest, HttpResponse, Result};
+
+// path: /record/{key}
+pub fn delete_record<S: RecordStore>(
+    store: web::Data<S>,
+    req: HttpRequest,
+) -> Result<HttpResponse> {
+    let key = req.match_info().get("key").unwrap();
+
+    // (key: String) -> (nano: NanoTime)
+    let nano = key_to_nano(key).ok_or_else(|| HandlerError::bad_request(ErrRes::bad_key()))?;
+
+    let record = store
+        .peek(nano)
+        .map_err(HandlerError::from)?
+        .ok_or_else(|| HandlerError::not_found(ErrRes::record_not_found()))?;
+
+    let token = req
+        .headers()
+        .get(DELETE_TOKEN_HEADER)
+        .and_then(|v| v.to_str().ok())
+        .unwrap_or("");
+
+    if !verify_token(&record.delete_token, token) {
+        return Err(HandlerError::forbidden(ErrRes::wrong_token()).into());
+    }
+
+    if !store.delete(nano).map_err(HandlerError::from)? {
+        return Err(HandlerError::not_found(ErrRes::record_not_found()).into());
+    }
+
+    info!("DELETE key = {}", key);
+    Ok(HttpResponse::NoContent().finish())
+}
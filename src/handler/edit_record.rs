@@ -0,0 +1,100 @@
+use super::save_record::resolve_files;
+use crate::data::dto::{EditRecordReq, EditRecordRes, ErrRes};
+use crate::data::encryption::is_valid_ciphertext;
+use crate::data::key::key_to_nano;
+use crate::data::lang::resolve;
+use crate::data::policy::{Submission, POLICY};
+use crate::data::token::verify_token;
+use crate::env::MAX_REVISIONS;
+use crate::shared::error::HandlerError;
+use crate::shared::resource::EDIT_TOKEN_HEADER;
+use crate::shared::store::RecordStore;
+use crate::time::{nano_to_sec, now_nano};
+
+use actix_web::{web, HttpRequest, HttpResponse, Result};
+
+// path: /record/{key}
+pub fn edit_record<S: RecordStore>(
+    store: web::Data<S>,
+    req: HttpRequest,
+    dto: web::Json<EditRecordReq>,
+) -> Result<HttpResponse> {
+    let key = req.match_info().get("key").unwrap();
+
+    // (key: String) -> (nano: NanoTime)
+    let nano = key_to_nano(key).ok_or_else(|| HandlerError::bad_request(ErrRes::bad_key()))?;
+
+    let record = store
+        .peek(nano)
+        .map_err(HandlerError::from)?
+        .ok_or_else(|| HandlerError::not_found(ErrRes::record_not_found()))?;
+
+    let token = req
+        .headers()
+        .get(EDIT_TOKEN_HEADER)
+        .and_then(|v| v.to_str().ok())
+        .unwrap_or("");
+
+    if !verify_token(&record.edit_token, token) {
+        return Err(HandlerError::forbidden(ErrRes::wrong_token()).into());
+    }
+
+    if record.is_binary() {
+        return Err(HandlerError::bad_request(ErrRes::binary_record()).into());
+    }
+
+    if record.is_encrypted() && !is_valid_ciphertext(&dto.content) {
+        return Err(HandlerError::bad_request(ErrRes::bad_ciphertext()).into());
+    }
+
+    let lang = resolve(&dto.lang, &dto.content, record.is_encrypted())
+        .ok_or_else(|| HandlerError::bad_request(ErrRes::unknown_lang()))?;
+
+    let EditRecordReq {
+        title,
+        content,
+        files,
+        ..
+    } = dto.into_inner();
+    let files = resolve_files(files, &content, record.is_encrypted())?;
+
+    POLICY
+        .check(&Submission {
+            title: &title,
+            content: &content,
+            files: &files,
+            encrypted: record.is_encrypted(),
+            binary: false,
+        })
+        .map_err(HandlerError::bad_request)?;
+
+    let editing_time = nano_to_sec(now_nano());
+    let mut revision = None;
+
+    // checked on the stored record, so concurrent edits can not pass MAX_REVISIONS
+    let found = store
+        .update(nano, |record| {
+            if record.revision() >= *MAX_REVISIONS {
+                revision = None;
+                return;
+            }
+            record.edit(
+                title.clone(),
+                lang.into(),
+                content.clone(),
+                files.clone(),
+                editing_time,
+            );
+            revision = Some(record.revision());
+        })
+        .map_err(HandlerError::from)?;
+
+    if !found {
+        return Err(HandlerError::not_found(ErrRes::record_not_found()).into());
+    }
+    let revision =
+        revision.ok_or_else(|| HandlerError::bad_request(ErrRes::too_many_revisions()))?;
+
+    info!("EDIT key = {}, revision = {}", key, revision);
+    Ok(HttpResponse::Ok().json(EditRecordRes { revision }))
+}
@@ -0,0 +1,24 @@
+use super::find_record::access_record;
+use crate::data::archive::{to_tar, MIME};
+use crate::shared::store::RecordStore;
+
+use actix_web::http::header;
+use actix_web::{web, HttpRequest, HttpResponse, Result};
+
+// path: /record/{key}/archive
+pub fn find_archive<S: RecordStore>(store: web::Data<S>, req: HttpRequest) -> Result<HttpResponse> {
+    let (record, _) = access_record(store.get_ref(), &req)?;
+
+    // keys are alphanumeric, so the file name needs no quoting
+    let key = req.match_info().get("key").unwrap();
+    let tar = to_tar(key, &record)?;
+
+    info!("FIND ARCHIVE key = {}, size = {}", key, tar.len());
+    Ok(HttpResponse::Ok()
+        .content_type(MIME)
+        .header(
+            header::CONTENT_DISPOSITION,
+            format!("attachment; filename=\"{}.tar\"", key),
+        )
+        .body(tar))
+}
@@ -0,0 +1,70 @@
+use super::find_html::{check_renderable, CSP};
+use super::find_record::{access_key, password_error, request_password};
+use crate::data::diff::{hunks, render_side_by_side, unified, DEFAULT_CONTEXT, MAX_CONTEXT};
+use crate::data::dto::{DiffQuery, DiffRes, ErrRes};
+use crate::data::key::key_to_nano;
+use crate::data::lang::mime_type;
+use crate::shared::error::HandlerError;
+use crate::shared::store::RecordStore;
+
+use actix_web::http::header;
+use actix_web::{web, HttpRequest, HttpResponse, Result};
+
+// path: /diff/{key_a}/{key_b}
+pub fn find_diff<S: RecordStore>(store: web::Data<S>, req: HttpRequest) -> Result<HttpResponse> {
+    let key_a = req.match_info().get("key_a").unwrap();
+    let key_b = req.match_info().get("key_b").unwrap();
+
+    let query = web::Query::<DiffQuery>::from_query(req.query_string())
+        .map(web::Query::into_inner)
+        .unwrap_or(DiffQuery {
+            format: None,
+            context: None,
+        });
+    let context = query.context.unwrap_or(DEFAULT_CONTEXT).min(MAX_CONTEXT);
+    let format = query.format.as_deref().unwrap_or("json");
+    if !["json", "diff", "html"].contains(&format) {
+        return Err(HandlerError::bad_request(ErrRes::bad_diff_format()).into());
+    }
+
+    // check both, password included, before counting a view of either
+    let password = request_password(&req);
+    for key in &[key_a, key_b] {
+        let nano = key_to_nano(key).ok_or_else(|| HandlerError::bad_request(ErrRes::bad_key()))?;
+        let record = check_renderable(store.get_ref(), nano)?;
+        if !record.check_password(password.as_deref()) {
+            return Err(password_error(password.as_deref()).into());
+        }
+    }
+    let (a, _) = access_key(store.get_ref(), &req, key_a)?;
+    // a record burnt by its first view is compared with itself
+    let b = if key_to_nano(key_b) == key_to_nano(key_a) {
+        a.clone()
+    } else {
+        access_key(store.get_ref(), &req, key_b)?.0
+    };
+
+    let hunks = hunks(&a.content, &b.content, context);
+
+    info!(
+        "DIFF key_a = {}, key_b = {}, hunks = {}",
+        key_a,
+        key_b,
+        hunks.len()
+    );
+    Ok(match format {
+        "diff" => HttpResponse::Ok()
+            .content_type(mime_type("diff"))
+            .body(unified(key_a, key_b, &hunks)),
+        "html" => HttpResponse::Ok()
+            .content_type("text/html; charset=utf-8")
+            .header(header::X_CONTENT_TYPE_OPTIONS, "nosniff")
+            .header(header::CONTENT_SECURITY_POLICY, CSP)
+            .body(render_side_by_side(&a.title, &b.title, &hunks)),
+        _ => HttpResponse::Ok().json(DiffRes {
+            key_a,
+            key_b,
+            hunks: &hunks,
+        }),
+    })
+}
@@ -0,0 +1,40 @@
+use super::find_record::access_record;
+use crate::data::dto::ErrRes;
+use crate::data::key::key_to_nano;
+use crate::data::lang::mime_type;
+use crate::shared::error::HandlerError;
+use crate::shared::store::RecordStore;
+
+use actix_web::http::header;
+use actix_web::{web, HttpRequest, HttpResponse, Result};
+
+// path: /record/{key}/files/{name}
+pub fn find_file<S: RecordStore>(store: web::Data<S>, req: HttpRequest) -> Result<HttpResponse> {
+    let key = req.match_info().get("key").unwrap();
+    let name = req.match_info().get("name").unwrap();
+    let nano = key_to_nano(key).ok_or_else(|| HandlerError::bad_request(ErrRes::bad_key()))?;
+    let file_not_found = || HandlerError::not_found(ErrRes::file_not_found());
+
+    // a wrong name should not count a view, unless that tells file names without the password
+    if let Some(record) = store.peek(nano).map_err(HandlerError::from)? {
+        if !record.is_protected() && record.find_file(name).is_none() {
+            return Err(file_not_found().into());
+        }
+    }
+
+    let (record, _) = access_record(store.get_ref(), &req)?;
+    let file = record.find_file(name).ok_or_else(file_not_found)?;
+
+    // ciphertext is opaque whatever `lang` says
+    let content_type = if record.is_encrypted() {
+        mime_type("")
+    } else {
+        mime_type(&file.lang)
+    };
+
+    info!("FIND FILE key = {}, name = {:?}", key, name);
+    Ok(HttpResponse::Ok()
+        .content_type(content_type)
+        .header(header::X_CONTENT_TYPE_OPTIONS, "nosniff")
+        .body(file.content.clone()))
+}
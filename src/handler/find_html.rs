@@ -0,0 +1,54 @@
+use super::find_record::{access_record, check_not_bundle};
+use crate::data::dto::ErrRes;
+use crate::data::highlight::render_page;
+use crate::data::key::key_to_nano;
+use crate::data::record::Record;
+use crate::shared::error::HandlerError;
+use crate::shared::store::RecordStore;
+use crate::time::NanoTime;
+
+use actix_web::http::header;
+use actix_web::{web, HttpRequest, HttpResponse, Result};
+
+pub(super) const CSP: &str =
+    "default-src 'none'; style-src 'unsafe-inline'; script-src 'unsafe-inline'";
+
+/// Ciphertext, bytes and bundles can not be rendered, refuses them before counting a view.
+/// Returns the record as peeked.
+pub(super) fn check_renderable<S: RecordStore>(
+    store: &S,
+    nano: NanoTime,
+) -> Result<Record, HandlerError<'static>> {
+    let record = check_not_bundle(store, nano)?;
+    if record.is_encrypted() {
+        return Err(HandlerError::bad_request(ErrRes::encrypted_record()));
+    }
+    if record.is_binary() {
+        return Err(HandlerError::bad_request(ErrRes::binary_record()));
+    }
+    Ok(record)
+}
+
+// path: /record/{key}/html
+pub fn find_html<S: RecordStore>(store: web::Data<S>, req: HttpRequest) -> Result<HttpResponse> {
+    let key = req.match_info().get("key").unwrap();
+    let nano = key_to_nano(key).ok_or_else(|| HandlerError::bad_request(ErrRes::bad_key()))?;
+
+    check_renderable(store.get_ref(), nano)?;
+
+    let (record, _) = access_record(store.get_ref(), &req)?;
+
+    // keep the password query for the raw link
+    let raw_href = match req.query_string() {
+        "" => String::from("raw"),
+        query => format!("raw?{}", query),
+    };
+    let html = render_page(&record.title, &record.lang, &record.content, &raw_href);
+
+    info!("FIND HTML key = {}", key);
+    Ok(HttpResponse::Ok()
+        .content_type("text/html; charset=utf-8")
+        .header(header::X_CONTENT_TYPE_OPTIONS, "nosniff")
+        .header(header::CONTENT_SECURITY_POLICY, CSP)
+        .body(html))
+}
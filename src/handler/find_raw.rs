@@ -0,0 +1,91 @@
+use super::find_record::{access_record, check_not_bundle};
+use crate::data::binary::content_disposition;
+use crate::data::dto::ErrRes;
+use crate::data::key::key_to_nano;
+use crate::data::lang::mime_type;
+use crate::shared::error::HandlerError;
+use crate::shared::store::RecordStore;
+
+use actix_web::http::header;
+use actix_web::{web, HttpRequest, HttpResponse, Result};
+
+// path: /record/{key}/raw
+pub fn find_raw<S: RecordStore>(store: web::Data<S>, req: HttpRequest) -> Result<HttpResponse> {
+    let key = req.match_info().get("key").unwrap();
+    let nano = key_to_nano(key).ok_or_else(|| HandlerError::bad_request(ErrRes::bad_key()))?;
+
+    check_not_bundle(store.get_ref(), nano)?;
+
+    let (record, _) = access_record(store.get_ref(), &req)?;
+
+    if let Some(ref binary) = record.binary {
+        // assert: binary content is valid base64
+        let bytes = base64::decode(&record.content).unwrap();
+        info!("FIND RAW key = {}, size = {}", key, bytes.len());
+        return Ok(HttpResponse::Ok()
+            .content_type(binary.content_type.as_str())
+            .header(header::X_CONTENT_TYPE_OPTIONS, "nosniff")
+            .header(
+                header::CONTENT_DISPOSITION,
+                content_disposition(&binary.file_name),
+            )
+            .body(bytes));
+    }
+
+    // ciphertext is opaque whatever `lang` says
+    let content_type = if record.is_encrypted() {
+        mime_type("")
+    } else {
+        mime_type(&record.lang)
+    };
+
+    info!("FIND RAW key = {}", key);
+    Ok(HttpResponse::Ok()
+        .content_type(content_type)
+        .header(header::X_CONTENT_TYPE_OPTIONS, "nosniff")
+        .body(record.content))
+}
+
+#[cfg(test)]
+#[test]
+fn test_find_bundle() {
+    use super::find_html::find_html;
+    use crate::data::record::{Record, RecordFile};
+    use crate::mem_store::State;
+
+    use actix_web::http::StatusCode;
+    use actix_web::test::TestRequest;
+
+    const KEY: &str = "CHAUV6HI74OCNO";
+    let nano = key_to_nano(KEY).unwrap();
+    let state = web::Data::new(State::new(1024 * 1024));
+    let mut record = Record::sample();
+    record.content = String::new();
+    record.files = vec![RecordFile {
+        name: "a.rs".into(),
+        lang: "rust".into(),
+        content: "// a".into(),
+    }];
+    record.burn_after_reading = true;
+    state.save(nano, record).unwrap();
+
+    let req = || TestRequest::default().param("key", KEY).to_http_request();
+    let status = |res: Result<HttpResponse>| {
+        res.unwrap_err()
+            .as_response_error()
+            .render_response()
+            .status()
+    };
+    assert_eq!(
+        status(find_raw(state.clone(), req())),
+        StatusCode::BAD_REQUEST
+    );
+    assert_eq!(
+        status(find_html(state.clone(), req())),
+        StatusCode::BAD_REQUEST
+    );
+
+    // neither counted a view, so the bundle is not burnt
+    let store = state.store_lock.read().unwrap();
+    assert_eq!(store.get(nano).unwrap().access_count, 0);
+}
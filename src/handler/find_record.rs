@@ -0,0 +1,106 @@
+use crate::data::dto::{ErrRes, FindRecordRes, PasswordQuery};
+use crate::data::key::key_to_nano;
+use crate::data::lang::canonical_id;
+use crate::data::record::Record;
+use crate::shared::error::HandlerError;
+use crate::shared::metrics;
+use crate::shared::resource::PASSWORD_HEADER;
+use crate::shared::store::{Access, RecordStore};
+use crate::time::NanoTime;
+
+use actix_web::{web, HttpRequest, HttpResponse, Result};
+
+/// Reads the password from the header, or else from the query string.
+pub(super) fn request_password(req: &HttpRequest) -> Option<String> {
+    if let Some(v) = req.headers().get(PASSWORD_HEADER) {
+        return v.to_str().ok().map(String::from);
+    }
+    web::Query::<PasswordQuery>::from_query(req.query_string())
+        .ok()
+        .and_then(|q| q.into_inner().password)
+}
+
+pub(super) fn password_error(password: Option<&str>) -> HandlerError<'static> {
+    match password {
+        None => HandlerError::unauthorized(ErrRes::password_required()),
+        Some(_) => HandlerError::forbidden(ErrRes::wrong_password()),
+    }
+}
+
+/// A bundle has no content of its own, refuses it before counting a view.
+/// Returns the record as peeked.
+pub(super) fn check_not_bundle<S: RecordStore>(
+    store: &S,
+    nano: NanoTime,
+) -> Result<Record, HandlerError<'static>> {
+    let record = store
+        .peek(nano)
+        .map_err(HandlerError::from)?
+        .ok_or_else(|| HandlerError::not_found(ErrRes::record_not_found()))?;
+    if record.is_bundle() {
+        return Err(HandlerError::bad_request(ErrRes::bundle_record()));
+    }
+    Ok(record)
+}
+
+/// Looks up `{key}` and counts a view. Shared by every handler that reads content.
+pub(super) fn access_record<S: RecordStore>(
+    store: &S,
+    req: &HttpRequest,
+) -> Result<(Record, u64), HandlerError<'static>> {
+    access_key(store, req, req.match_info().get("key").unwrap())
+}
+
+/// Looks up `key` with the password of `req` and counts a view.
+pub(super) fn access_key<S: RecordStore>(
+    store: &S,
+    req: &HttpRequest,
+    key: &str,
+) -> Result<(Record, u64), HandlerError<'static>> {
+    // (key: String) -> (nano: NanoTime)
+    let nano = key_to_nano(key).ok_or_else(|| HandlerError::bad_request(ErrRes::bad_key()))?;
+
+    let password = request_password(req);
+    let password = password.as_deref();
+
+    // access record
+    let access = store
+        .access(nano, |record| record.check_password(password))
+        .map_err(HandlerError::from)?
+        .ok_or_else(|| HandlerError::not_found(ErrRes::record_not_found()))?;
+
+    match access {
+        Access::Found(record, view_count) => {
+            metrics::FINDS.inc();
+            Ok((record, view_count))
+        }
+        Access::Denied => Err(password_error(password)),
+    }
+}
+
+// path: /record/{key}
+pub fn find_record<S: RecordStore>(store: web::Data<S>, req: HttpRequest) -> Result<HttpResponse> {
+    let (record, view_count) = access_record(store.get_ref(), &req)?;
+
+    // construct response
+    let resp = FindRecordRes {
+        title: &record.title,
+        lang: canonical_id(&record.lang),
+        content: &record.content,
+        saving_time: record.saving_time,
+        expiration: record.expiration,
+        view_count,
+        revision: record.revision(),
+        burn_after_reading: record.burn_after_reading,
+        max_views: record.max_views,
+        encrypted: record.is_encrypted(),
+        encryption_version: record.encryption_version,
+        files: &record.files,
+        binary: record.binary.as_ref(),
+        parent_key: record.parent_key.as_deref(),
+        fork_count: record.fork_count,
+    };
+
+    info!("FIND key = {}", req.match_info().get("key").unwrap());
+    Ok(HttpResponse::Ok().json(resp))
+}
@@ -0,0 +1,73 @@
+use super::find_record::{password_error, request_password};
+use crate::data::dto::{ErrRes, FindRevisionRes};
+use crate::data::key::key_to_nano;
+use crate::data::lang::canonical_id;
+use crate::shared::error::HandlerError;
+use crate::shared::store::RecordStore;
+
+use actix_web::{web, HttpRequest, HttpResponse, Result};
+
+// path: /record/{key}/revisions/{revision}
+pub fn find_revision<S: RecordStore>(
+    store: web::Data<S>,
+    req: HttpRequest,
+) -> Result<HttpResponse> {
+    let key = req.match_info().get("key").unwrap();
+
+    // (key: String) -> (nano: NanoTime)
+    let nano = key_to_nano(key).ok_or_else(|| HandlerError::bad_request(ErrRes::bad_key()))?;
+
+    let revision_not_found = || HandlerError::not_found(ErrRes::revision_not_found());
+
+    let revision: usize = req
+        .match_info()
+        .get("revision")
+        .unwrap()
+        .parse()
+        .map_err(|_| revision_not_found())?;
+
+    // read record, not counted as a view
+    let record = store
+        .peek(nano)
+        .map_err(HandlerError::from)?
+        .ok_or_else(|| HandlerError::not_found(ErrRes::record_not_found()))?;
+
+    let password = request_password(&req);
+    let password = password.as_deref();
+    if !record.check_password(password) {
+        return Err(password_error(password).into());
+    }
+
+    // revisions can not be read without counting views
+    if record.view_limit().is_some() {
+        return Err(HandlerError::forbidden(ErrRes::limited_views()).into());
+    }
+
+    // construct response
+    let resp = if revision == record.revision() {
+        FindRevisionRes {
+            title: &record.title,
+            lang: canonical_id(&record.lang),
+            content: &record.content,
+            editing_time: record.editing_time,
+            revision,
+            files: &record.files,
+        }
+    } else {
+        let prior = record
+            .revisions
+            .get(revision)
+            .ok_or_else(revision_not_found)?;
+        FindRevisionRes {
+            title: &prior.title,
+            lang: canonical_id(&prior.lang),
+            content: &prior.content,
+            editing_time: prior.editing_time,
+            revision,
+            files: &prior.files,
+        }
+    };
+
+    info!("FIND key = {}, revision = {}", key, revision);
+    Ok(HttpResponse::Ok().json(resp))
+}
@@ -0,0 +1,60 @@
+use super::find_record::access_record;
+use super::save_record::save;
+use crate::data::dto::{FileReq, ForkRecordReq, SaveRecordReq};
+use crate::data::key::{key_to_nano, nano_to_key};
+use crate::data::lang::canonical_id;
+use crate::shared::error::HandlerError;
+use crate::shared::store::RecordStore;
+
+use actix_web::{web, HttpRequest, HttpResponse, Result};
+
+// path: /record/{key}/fork
+pub fn fork_record<S: RecordStore>(
+    store: web::Data<S>,
+    req: HttpRequest,
+    dto: web::Json<ForkRecordReq>,
+) -> Result<HttpResponse> {
+    // reading the parent counts a view and needs its password
+    let (parent, _) = access_record(store.get_ref(), &req)?;
+
+    // assert: access_record checked the key
+    let parent_nano = key_to_nano(req.match_info().get("key").unwrap()).unwrap();
+    let parent_key = nano_to_key(parent_nano);
+
+    // the current revision only, with settings of its own
+    let dto = dto.into_inner();
+    let fork = SaveRecordReq {
+        title: parent.title,
+        lang: canonical_id(&parent.lang).into(),
+        content: parent.content,
+        files: parent
+            .files
+            .into_iter()
+            .map(|f| FileReq {
+                name: f.name,
+                lang: f.lang,
+                content: f.content,
+            })
+            .collect(),
+        expiration: dto.expiration,
+        burn_after_reading: dto.burn_after_reading,
+        max_views: dto.max_views,
+        password: dto.password,
+        encrypted: parent.encryption_version.is_some(),
+        encryption_version: parent.encryption_version,
+    };
+    let resp = save(
+        store.get_ref(),
+        fork,
+        parent.binary,
+        Some(parent_key.clone()),
+    )?;
+
+    // the parent may be gone if this view exhausted it
+    store
+        .update(parent_nano, |record| record.fork_count += 1)
+        .map_err(HandlerError::from)?;
+
+    info!("FORK parent_key = {}", parent_key);
+    Ok(resp)
+}
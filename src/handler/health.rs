@@ -0,0 +1,44 @@
+use crate::data::dto::{HealthRes, ReadyRes};
+use crate::shared::store::RecordStore;
+
+use actix_web::http::StatusCode;
+use actix_web::{web, HttpResponse};
+
+// path: /healthz
+pub fn healthz() -> HttpResponse {
+    HttpResponse::Ok().json(HealthRes { status: "ok" })
+}
+
+// path: /readyz
+pub fn readyz<S: RecordStore>(store: web::Data<S>) -> HttpResponse {
+    let res = match store.health() {
+        Ok(health) => {
+            let stats = health.stats;
+            ReadyRes {
+                ready: health.is_ready(),
+                store_size: stats.map(|s| s.store_size),
+                item_count: stats.map(|s| s.item_count),
+                max_store_size: health.max_store_size,
+                utilization: stats
+                    .zip(health.max_store_size)
+                    .map(|(s, max)| s.store_size as f64 / max as f64),
+                gc_running: health.gc_running,
+                error: None,
+            }
+        }
+        Err(err) => {
+            error!("READYZ {}", err);
+            ReadyRes {
+                error: Some(err.to_string()),
+                ..ReadyRes::default()
+            }
+        }
+    };
+
+    let status = if res.ready {
+        StatusCode::OK
+    } else {
+        StatusCode::SERVICE_UNAVAILABLE
+    };
+    HttpResponse::build(status).json(res)
+}
@@ -0,0 +1,8 @@
+use crate::shared::metrics::{render, CONTENT_TYPE};
+
+use actix_web::HttpResponse;
+
+// path: /metrics
+pub fn metrics() -> HttpResponse {
+    HttpResponse::Ok().content_type(CONTENT_TYPE).body(render())
+}
@@ -0,0 +1,162 @@
+use crate::data::dto::{ErrRes, FileReq, SaveRecordReq, SaveRecordRes};
+use crate::data::encryption::{is_supported_version, is_valid_ciphertext, DEFAULT_VERSION};
+use crate::data::key::nano_to_key;
+use crate::data::lang::{resolve, resolve_named};
+use crate::data::password::hash_password;
+use crate::data::policy::{Submission, POLICY};
+use crate::data::record::{Binary, Record, RecordFile};
+use crate::data::token::gen_token;
+use crate::env::{MAX_EXPIRATION, MAX_FILES};
+use crate::shared::error::HandlerError;
+use crate::shared::metrics;
+use crate::shared::store::RecordStore;
+use crate::time::{nano_to_sec, now_nano, sec_to_nano};
+
+use std::collections::HashSet;
+
+use actix_web::{web, HttpResponse, Result};
+
+/// Checks the files of a bundle and resolves their languages. Shared by saving and editing.
+pub(super) fn resolve_files(
+    files: Vec<FileReq>,
+    content: &str,
+    encrypted: bool,
+) -> Result<Vec<RecordFile>, HandlerError<'static>> {
+    if !files.is_empty() && !content.is_empty() {
+        return Err(HandlerError::bad_request(ErrRes::content_with_files()));
+    }
+    if files.len() > *MAX_FILES {
+        return Err(HandlerError::bad_request(ErrRes::too_many_files()));
+    }
+
+    let mut names = HashSet::new();
+    files
+        .into_iter()
+        .map(|f| {
+            if !RecordFile::is_valid_name(&f.name) || !names.insert(f.name.clone()) {
+                return Err(HandlerError::bad_request(ErrRes::bad_file_name()));
+            }
+            if encrypted && !is_valid_ciphertext(&f.content) {
+                return Err(HandlerError::bad_request(ErrRes::bad_ciphertext()));
+            }
+            let lang = resolve_named(&f.lang, &f.name, &f.content, encrypted)
+                .ok_or_else(|| HandlerError::bad_request(ErrRes::unknown_lang()))?;
+            Ok(RecordFile {
+                name: f.name,
+                lang: lang.into(),
+                content: f.content,
+            })
+        })
+        .collect()
+}
+
+// path: /record
+pub fn save_record<S: RecordStore>(
+    store: web::Data<S>,
+    dto: web::Json<SaveRecordReq>,
+) -> Result<HttpResponse> {
+    save(store.get_ref(), dto.into_inner(), None, None)
+}
+
+/// Checks `dto` and saves a new record. Shared by saving, uploading and forking.
+pub(super) fn save<S: RecordStore>(
+    store: &S,
+    dto: SaveRecordReq,
+    binary: Option<Binary>,
+    parent_key: Option<String>,
+) -> Result<HttpResponse> {
+    if dto.expiration > *MAX_EXPIRATION {
+        return Err(HandlerError::bad_request(ErrRes::too_long_expiration()).into());
+    }
+
+    if dto.max_views == Some(0) {
+        return Err(HandlerError::bad_request(ErrRes::bad_max_views()).into());
+    }
+
+    let encryption_version = if dto.encrypted {
+        let version = dto.encryption_version.unwrap_or(DEFAULT_VERSION);
+        if !is_supported_version(version) {
+            return Err(HandlerError::bad_request(ErrRes::bad_encryption_version()).into());
+        }
+        if !is_valid_ciphertext(&dto.content) {
+            return Err(HandlerError::bad_request(ErrRes::bad_ciphertext()).into());
+        }
+        Some(version)
+    } else {
+        None
+    };
+
+    let lang = resolve(&dto.lang, &dto.content, dto.encrypted)
+        .ok_or_else(|| HandlerError::bad_request(ErrRes::unknown_lang()))?;
+
+    let files = resolve_files(dto.files, &dto.content, dto.encrypted)?;
+
+    POLICY
+        .check(&Submission {
+            title: &dto.title,
+            content: &dto.content,
+            files: &files,
+            encrypted: dto.encrypted,
+            binary: binary.is_some(),
+        })
+        .map_err(HandlerError::bad_request)?;
+
+    let now = now_nano();
+    let saving_time = nano_to_sec(now);
+    let dead_time = now + sec_to_nano(dto.expiration); // assert: now.add(expiraton) never overflows
+
+    let delete_token = gen_token();
+    let edit_token = gen_token();
+
+    let password_hash = dto
+        .password
+        .as_ref()
+        .filter(|p| !p.is_empty())
+        .map(|p| hash_password(p));
+
+    let record = Record {
+        title: dto.title,
+        lang: lang.into(),
+        content: dto.content,
+        saving_time,
+        editing_time: saving_time,
+        expiration: dto.expiration,
+        dead_time,
+        delete_token: delete_token.clone(),
+        edit_token: edit_token.clone(),
+        burn_after_reading: dto.burn_after_reading,
+        max_views: dto.max_views,
+        password_hash,
+        encryption_version,
+        sealed_key_id: None,
+        revisions: Vec::new(),
+        files,
+        binary,
+        parent_key,
+        fork_count: 0,
+        packed: None,
+        body: None,
+    };
+
+    store.save(now, record).map_err(HandlerError::from)?;
+
+    let key = nano_to_key(now);
+    metrics::SAVES.inc();
+
+    match store.stats() {
+        Some(stats) => {
+            metrics::set_store_stats(stats.store_size, stats.item_count);
+            info!(
+                "SAVE key = {}, store_size = {}, item_count = {}",
+                key, stats.store_size, stats.item_count
+            )
+        }
+        None => info!("SAVE key = {}", key),
+    }
+
+    Ok(HttpResponse::Ok().json(SaveRecordRes {
+        key: &key,
+        delete_token: &delete_token,
+        edit_token: &edit_token,
+    }))
+}
@@ -0,0 +1,88 @@
+use super::save_record::save;
+use crate::data::binary::{sanitize_content_type, sanitize_file_name};
+use crate::data::dto::{ErrRes, SaveRecordReq};
+use crate::data::lang::PLAINTEXT;
+use crate::data::multipart::{boundary, parse};
+use crate::data::record::Binary;
+use crate::env::MAX_UPLOAD_SIZE;
+use crate::shared::error::HandlerError;
+use crate::shared::store::RecordStore;
+
+use std::collections::HashMap;
+use std::str::FromStr;
+
+use actix_web::http::header;
+use actix_web::{web, HttpRequest, HttpResponse, Result};
+
+const FILE_FIELD: &str = "file";
+
+// path: /upload
+pub fn upload_record<S: RecordStore>(
+    store: web::Data<S>,
+    req: HttpRequest,
+    body: web::Bytes,
+) -> Result<HttpResponse> {
+    let bad_multipart = || HandlerError::bad_request(ErrRes::bad_multipart());
+
+    let boundary = req
+        .headers()
+        .get(header::CONTENT_TYPE)
+        .and_then(|v| v.to_str().ok())
+        .and_then(boundary)
+        .ok_or_else(bad_multipart)?;
+    let parts = parse(&body, &boundary).ok_or_else(bad_multipart)?;
+
+    // the file and text fields of SaveRecordReq
+    let mut file = None;
+    let mut form = HashMap::new();
+    for part in parts {
+        if part.name == FILE_FIELD {
+            file = Some(part);
+        } else {
+            let value = String::from_utf8(part.body.to_vec()).map_err(|_| bad_multipart())?;
+            form.insert(part.name, value);
+        }
+    }
+    let file = file.ok_or_else(bad_multipart)?;
+    if file.body.len() > *MAX_UPLOAD_SIZE {
+        return Err(HandlerError::bad_request(ErrRes::too_large_file()).into());
+    }
+
+    fn field<T: FromStr>(
+        form: &HashMap<String, String>,
+        name: &str,
+    ) -> Result<Option<T>, HandlerError<'static>> {
+        match form.get(name).map(|v| v.trim()).filter(|v| !v.is_empty()) {
+            None => Ok(None),
+            Some(v) => v
+                .parse()
+                .map(Some)
+                .map_err(|_| HandlerError::bad_request(ErrRes::bad_multipart())),
+        }
+    }
+
+    let binary = Binary {
+        file_name: sanitize_file_name(file.file_name.as_deref().unwrap_or("")),
+        content_type: sanitize_content_type(file.content_type.as_deref()),
+        size: file.body.len() as u64,
+    };
+
+    let dto = SaveRecordReq {
+        title: field(&form, "title")?.unwrap_or_else(|| binary.file_name.clone()),
+        lang: PLAINTEXT.into(),
+        content: base64::encode(file.body),
+        files: Vec::new(),
+        expiration: field(&form, "expiration")?.ok_or_else(bad_multipart)?,
+        burn_after_reading: field(&form, "burn_after_reading")?.unwrap_or(false),
+        max_views: field(&form, "max_views")?,
+        password: field(&form, "password")?,
+        encrypted: false,
+        encryption_version: None,
+    };
+
+    info!(
+        "UPLOAD file_name = {:?}, content_type = {}, size = {}",
+        binary.file_name, binary.content_type, binary.size
+    );
+    save(store.get_ref(), dto, Some(binary), None)
+}
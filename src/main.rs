@@ -1,26 +1,90 @@
 mod data;
 mod env;
+mod handler;
+mod server;
 mod shared;
 mod time;
 
+mod fs_store;
 mod mem_store;
 mod redis_store;
+mod sqlite_store;
 
 #[macro_use]
 extern crate lazy_static;
 #[macro_use]
 extern crate log;
 
-use crate::env::{info_env, REDIS_URL};
+use crate::data::compress::Compressor;
+use crate::data::policy::POLICY;
+use crate::data::seal::Sealer;
+use crate::env::{
+    info_env, AT_REST_KEYS, COMPRESSION_LEVEL, FS_DIR, POLICY_PATH, REDIS_URL, SQLITE_PATH,
+};
+use crate::server::run_server;
+use crate::shared::compressed_store::CompressedStore;
+use crate::shared::sealed_store::SealedStore;
+
+use std::sync::Arc;
 
 fn main() -> std::io::Result<()> {
     dotenv::dotenv().ok();
     env_logger::init();
     info_env();
+    crate::shared::metrics::init();
+
+    if let Some(ref path) = *POLICY_PATH {
+        info!("POLICY_PATH: {}, {} filters", path, POLICY.filter_count());
+    }
+
+    let sealer = AT_REST_KEYS.as_ref().map(|keys| {
+        let sealer = Sealer::parse(keys).expect("Invalid PASTEBIN_AT_REST_KEYS");
+        info!(
+            "AT_REST_KEYS: {} keys, sealing with {}",
+            sealer.key_count(),
+            sealer.current_key_id()
+        );
+        Arc::new(sealer)
+    });
+
+    // 0 disables compression
+    let compressor = Some(*COMPRESSION_LEVEL)
+        .filter(|&level| level != 0)
+        .map(Compressor::new);
 
-    if REDIS_URL.is_none() {
-        crate::mem_store::run_server()
+    if REDIS_URL.is_some() {
+        info!("COMPRESSION_LEVEL: {}", *COMPRESSION_LEVEL);
+        run_server(
+            move || {
+                let store = SealedStore::new(crate::redis_store::init(), sealer.clone());
+                CompressedStore::new(store, compressor)
+            },
+            None,
+        )
+    } else if SQLITE_PATH.is_some() {
+        crate::sqlite_store::start();
+        run_server(
+            move || SealedStore::new(crate::sqlite_store::init(), sealer.clone()),
+            None,
+        )
+    } else if FS_DIR.is_some() {
+        crate::fs_store::start();
+        run_server(
+            move || SealedStore::new(crate::fs_store::init(), sealer.clone()),
+            None,
+        )
     } else {
-        crate::redis_store::run_server()
+        info!("COMPRESSION_LEVEL: {}", *COMPRESSION_LEVEL);
+        let state = crate::mem_store::init()?;
+        let store = state.clone();
+        run_server(
+            move || {
+                let store = SealedStore::new(store.clone(), sealer.clone());
+                CompressedStore::new(store, compressor)
+            },
+            Some(1),
+        )?;
+        crate::mem_store::shutdown(&state);
+        Ok(())
     }
 }
@@ -1,5 +0,0 @@
-mod find_record;
-mod save_record;
-
-pub use self::find_record::find_record;
-pub use self::save_record::save_record;
@@ -1,19 +1,33 @@
-mod handler;
+mod snapshot;
 mod state;
 mod store;
+mod wal;
 
-pub use store::{LruValueSize, WithDeadTime};
+pub use self::state::State;
+pub use self::store::{LruValueSize, SharedPart, WithDeadTime};
+pub use self::wal::Fsync;
 
-use self::handler::{find_record, save_record};
-use self::state::{State, Store, StoreLock};
-use crate::env::{ADDR, CLEAN_DURATION, MAX_STORE_SIZE};
-use crate::shared::resource::{json_post_config, FIND_RECORD_ROUTE, SAVE_RECORD_ROUTE};
+use self::state::Store;
+use self::wal::Wal;
+use crate::env::{
+    CLEAN_DURATION, MAX_STORE_SIZE, SNAPSHOT_DURATION, SNAPSHOT_PATH, WAL_FSYNC, WAL_PATH,
+};
+use crate::shared::metrics;
 use crate::time::{now_nano, NanoTime};
 
+use std::io;
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Arc;
 use std::thread;
 use std::time::Duration;
 
-use actix_web::{web, App, HttpServer};
+/// `path` with `.tmp` appended, so that `store.json` and `store.log` do not share one.
+fn tmp_path(path: &Path) -> PathBuf {
+    let mut tmp = path.as_os_str().to_owned();
+    tmp.push(".tmp");
+    tmp.into()
+}
 
 fn gc(store: &mut Store, now: NanoTime) {
     let before_size = store.total_value_size();
@@ -26,47 +40,120 @@ fn gc(store: &mut Store, now: NanoTime) {
     let stw_time = now_nano() - now;
     let after_size = store.total_value_size();
     let after_count = store.item_count();
+    let shared_count = store.shared_count();
+    metrics::GC_PAUSE.observe(stw_time as f64 / 1e9);
+    metrics::GC_REMOVED.inc_by(removed_count as u64);
 
     info!(
-        "CLEAN stw: {} ns, removed: {}, store_size: {} -> {}, item_count: {} -> {}",
-        stw_time, removed_count, before_size, after_size, before_count, after_count
+        "CLEAN stw: {} ns, removed: {}, store_size: {} -> {}, item_count: {} -> {}, shared_count: {}",
+        stw_time, removed_count, before_size, after_size, before_count, after_count, shared_count
     );
 }
 
-fn start_gc(store_lock: StoreLock) {
+fn sync_wal(wal: &Wal) {
+    if let Err(err) = wal.sync() {
+        error!("WAL: {}", err);
+    }
+    match wal.compact() {
+        Ok(Some(kept)) => info!("WAL compacted: {} lines kept", kept),
+        Ok(None) => {}
+        Err(err) => error!("WAL: {}", err),
+    }
+}
+
+/// Clears the flag when the GC thread exits, by panic included.
+struct GcRunning(Arc<AtomicBool>);
+
+impl Drop for GcRunning {
+    fn drop(&mut self) {
+        self.0.store(false, Ordering::Relaxed);
+        error!("GC thread stopped");
+    }
+}
+
+fn start_gc(state: State) {
+    state.gc_running.store(true, Ordering::Relaxed);
+    let running = GcRunning(state.gc_running.clone());
+    thread::spawn(move || {
+        let _running = running;
+        loop {
+            // write store
+            // assert: store_lock.write never returns Err or paincs
+            let mut store = state.store_lock.write().unwrap();
+            let now = now_nano();
+            if store.needs_clean(now) {
+                gc(&mut store, now);
+            }
+            metrics::LRU_EVICTED.inc_by(store.take_evicted());
+            metrics::set_store_stats(store.total_value_size(), store.item_count());
+
+            // release writer lock
+            drop(store);
+
+            if let Some(ref wal) = state.wal {
+                sync_wal(wal);
+            }
+
+            thread::sleep(Duration::from_millis(*CLEAN_DURATION));
+        }
+    });
+}
+
+fn start_snapshot(state: State, path: PathBuf) {
     thread::spawn(move || loop {
-        // write store
+        thread::sleep(Duration::from_millis(*SNAPSHOT_DURATION));
+        snapshot::save_and_log(&state, &path);
+    });
+}
+
+pub fn init() -> io::Result<State> {
+    let mut state = State::new(*MAX_STORE_SIZE);
+
+    if WAL_PATH.is_some() && SNAPSHOT_PATH.is_none() {
+        return Err(io::Error::new(
+            io::ErrorKind::InvalidInput,
+            "PASTEBIN_WAL_PATH requires PASTEBIN_SNAPSHOT_PATH",
+        ));
+    }
+
+    if let Some(ref path) = *SNAPSHOT_PATH {
+        let path = Path::new(path);
         // assert: store_lock.write never returns Err or paincs
-        let mut store = store_lock.write().unwrap();
-        let now = now_nano();
-        if store.needs_clean(now) {
-            gc(&mut *store, now);
-        }
+        let mut store = state.store_lock.write().unwrap();
+        let (loaded, dropped, wal_seq) = snapshot::load(&mut store, path, now_nano())?;
+        info!(
+            "SNAPSHOT loaded: {} items, dropped: {} expired, store_size: {}",
+            loaded,
+            dropped,
+            store.total_value_size()
+        );
 
-        // release writer lock
+        if let Some(ref wal_path) = *WAL_PATH {
+            let wal_path = Path::new(wal_path);
+            let (applied, last_seq) = wal::replay(&mut store, wal_path, wal_seq, now_nano())?;
+            info!(
+                "WAL replayed: {} lines, item_count: {}, store_size: {}",
+                applied,
+                store.item_count(),
+                store.total_value_size()
+            );
+            state.wal = Some(Arc::new(Wal::open(wal_path, *WAL_FSYNC, last_seq)?));
+        }
         drop(store);
 
-        thread::sleep(Duration::from_millis(*CLEAN_DURATION));
-    });
+        start_snapshot(state.clone(), path.to_owned());
+    }
+
+    start_gc(state.clone());
+    Ok(state)
 }
 
-pub fn run_server() -> std::io::Result<()> {
-    let state = State::new(*MAX_STORE_SIZE);
-    start_gc(state.store_lock.clone());
-
-    info!("server start at {}", &*ADDR);
-
-    HttpServer::new(move || {
-        App::new()
-            .data(state.clone())
-            .service(web::resource(FIND_RECORD_ROUTE).route(web::get().to(find_record)))
-            .service(
-                web::resource(SAVE_RECORD_ROUTE)
-                    .route(web::post().to(save_record))
-                    .data(json_post_config()),
-            )
-    })
-    .workers(1)
-    .bind(&*ADDR)?
-    .run()
+/// Takes the last snapshot after the server stops.
+pub fn shutdown(state: &State) {
+    if let Some(ref path) = *SNAPSHOT_PATH {
+        snapshot::save_and_log(state, Path::new(path));
+    }
+    if let Some(ref wal) = state.wal {
+        sync_wal(wal);
+    }
 }
@@ -0,0 +1,132 @@
+use super::state::{State, Store};
+use super::tmp_path;
+use crate::data::record::Record;
+use crate::time::{now_nano, NanoTime};
+
+use std::fs::{self, File};
+use std::io::{self, BufReader, Write};
+use std::path::Path;
+
+use serde::{Deserialize, Serialize};
+
+#[derive(Serialize, Deserialize)]
+struct Entry<V> {
+    key: NanoTime,
+    access_count: u64,
+    queue_slot: NanoTime,
+    value: V,
+}
+
+#[derive(Serialize, Deserialize)]
+struct Snapshot<V> {
+    wal_seq: u64, // the last log line covered
+    items: Vec<Entry<V>>,
+}
+
+/// Writes all items in LRU order, atomically replacing `path`.
+/// Returns the number of items and the last log line covered.
+pub fn save(state: &State, path: &Path) -> io::Result<(usize, u64)> {
+    // serialize under the reader lock, write without it
+    let (buf, count, wal_seq) = {
+        // assert: store_lock.read never returns Err or paincs
+        let store = state.store_lock.read().unwrap();
+        let wal_seq = match state.wal {
+            Some(ref wal) => wal.checkpoint()?,
+            None => 0,
+        };
+        let items: Vec<Entry<&Record>> = store
+            .iter()
+            .map(|(&key, item)| Entry {
+                key,
+                access_count: item.access_count,
+                queue_slot: item.queue_slot(),
+                value: &item.value,
+            })
+            .collect();
+        let count = items.len();
+        let snapshot = Snapshot { wal_seq, items };
+        (serde_json::to_vec(&snapshot)?, count, wal_seq)
+    };
+
+    let tmp_path = tmp_path(path);
+    let mut file = File::create(&tmp_path)?;
+    file.write_all(&buf)?;
+    file.sync_all()?;
+    fs::rename(&tmp_path, path)?;
+    Ok((count, wal_seq))
+}
+
+/// Restores items which are still alive at `now`. A missing file is an empty snapshot.
+/// Returns the number of loaded items, dropped items and the last log line covered.
+pub fn load(store: &mut Store, path: &Path, now: NanoTime) -> io::Result<(usize, usize, u64)> {
+    let file = match File::open(path) {
+        Ok(f) => f,
+        Err(ref e) if e.kind() == io::ErrorKind::NotFound => return Ok((0, 0, 0)),
+        Err(e) => return Err(e),
+    };
+    let snapshot: Snapshot<Record> = serde_json::from_reader(BufReader::new(file))?;
+
+    let mut loaded = 0;
+    let mut dropped = 0;
+    for e in snapshot.items {
+        if e.value.dead_time <= now {
+            dropped += 1;
+            continue;
+        }
+        store.restore(e.key, e.value, e.access_count, e.queue_slot);
+        loaded += 1;
+    }
+    Ok((loaded, dropped, snapshot.wal_seq))
+}
+
+/// Saves a snapshot and lets the log drop the lines it covers.
+pub fn save_and_log(state: &State, path: &Path) {
+    let start = now_nano();
+    match save(state, path) {
+        Ok((count, wal_seq)) => {
+            info!(
+                "SNAPSHOT saved: {} items in {} ns",
+                count,
+                now_nano() - start
+            );
+            if let Some(ref wal) = state.wal {
+                wal.set_durable(wal_seq);
+            }
+        }
+        Err(err) => error!("SNAPSHOT: {}", err),
+    }
+}
+
+#[cfg(test)]
+#[test]
+fn test_snapshot() {
+    let path = std::env::temp_dir().join(format!("pastebin-snapshot-{}.json", now_nano()));
+    let state = State::new(1024 * 1024);
+    {
+        let mut store = state.store_lock.write().unwrap();
+        for i in 1..=3 {
+            let mut record = Record::sample();
+            record.dead_time = i * 100;
+            store.save(i, record);
+        }
+        store.access(2);
+    }
+    assert_eq!(save(&state, &path).unwrap(), (3, 0));
+    assert!(!tmp_path(&path).exists());
+    assert_eq!(
+        tmp_path(Path::new("a/store.json")),
+        Path::new("a/store.json.tmp")
+    );
+
+    let mut store = Store::new(1024 * 1024);
+    assert_eq!(load(&mut store, &path, 150).unwrap(), (2, 1, 0));
+    assert_eq!(
+        store.iter().map(|(&k, _)| k).collect::<Vec<_>>(),
+        vec![3, 2]
+    );
+    assert_eq!(store.get(2).unwrap().access_count, 1);
+
+    fs::remove_file(&path).unwrap();
+    let mut store = Store::new(1024 * 1024);
+    assert_eq!(load(&mut store, &path, 150).unwrap(), (0, 0, 0));
+}
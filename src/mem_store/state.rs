@@ -1,7 +1,10 @@
 use super::store;
+use super::wal::Wal;
 use crate::data::record::Record;
+use crate::shared::store::{Access, RecordStore, StoreError, StoreHealth, StoreResult, StoreStats};
 use crate::time::NanoTime;
 
+use std::sync::atomic::{AtomicBool, Ordering};
 use std::sync::{Arc, RwLock};
 
 pub type Store = store::Store<NanoTime, Record>;
@@ -10,12 +13,121 @@ pub type StoreLock = Arc<RwLock<Store>>;
 #[derive(Clone)]
 pub struct State {
     pub store_lock: StoreLock,
+    pub wal: Option<Arc<Wal>>,
+    pub gc_running: Arc<AtomicBool>, // set by start_gc, cleared when its thread exits
 }
 
 impl State {
     pub fn new(max_value_size: usize) -> Self {
         Self {
             store_lock: Arc::new(RwLock::new(Store::new(max_value_size))),
+            wal: None,
+            gc_running: Arc::new(AtomicBool::new(false)),
         }
     }
 }
+
+// assert: store_lock.write and store_lock.read never return Err or paincs
+// changes are logged before they are applied, under the writer lock
+// a record larger than the whole store is rejected before that
+impl RecordStore for State {
+    fn save(&self, nano: NanoTime, record: Record) -> StoreResult<()> {
+        let mut store = self.store_lock.write().unwrap();
+        if !store.fits(&record) {
+            return Err(StoreError::TooLarge);
+        }
+        if let Some(ref wal) = self.wal {
+            wal.log_save(nano, &record)?;
+        }
+        store.save(nano, record);
+        Ok(())
+    }
+
+    fn access<G: FnOnce(&Record) -> bool>(
+        &self,
+        nano: NanoTime,
+        guard: G,
+    ) -> StoreResult<Option<Access>> {
+        let mut store = self.store_lock.write().unwrap();
+
+        let exhausted = match store.get(nano) {
+            None => return Ok(None),
+            Some(item) if item.value.is_protected() && !guard(&item.value) => {
+                return Ok(Some(Access::Denied));
+            }
+            Some(item) => item.value.is_exhausted(item.access_count + 1),
+        };
+
+        if let Some(ref wal) = self.wal {
+            if exhausted {
+                wal.log_remove(nano)?;
+            } else {
+                wal.log_access(nano);
+            }
+        }
+
+        let ans = if exhausted {
+            store.remove(nano).map(|mut item| {
+                item.access_count += 1;
+                Access::Found(item.value, item.access_count)
+            })
+        } else {
+            store
+                .access(nano)
+                .map(|item| Access::Found(item.value.clone(), item.access_count))
+        };
+        Ok(ans)
+    }
+
+    fn peek(&self, nano: NanoTime) -> StoreResult<Option<Record>> {
+        let store = self.store_lock.read().unwrap();
+        Ok(store.get(nano).map(|item| item.value.clone()))
+    }
+
+    fn update<F: FnMut(&mut Record)>(&self, nano: NanoTime, mut f: F) -> StoreResult<bool> {
+        let mut store = self.store_lock.write().unwrap();
+        let mut value = match store.get(nano) {
+            Some(item) => item.value.clone(),
+            None => return Ok(false),
+        };
+        f(&mut value);
+        if !store.fits(&value) {
+            return Err(StoreError::TooLarge);
+        }
+        if let Some(ref wal) = self.wal {
+            wal.log_update(nano, &value)?;
+        }
+        Ok(store.update(nano, |v| *v = value).is_some())
+    }
+
+    fn delete(&self, nano: NanoTime) -> StoreResult<bool> {
+        let mut store = self.store_lock.write().unwrap();
+        if store.get(nano).is_none() {
+            return Ok(false);
+        }
+        if let Some(ref wal) = self.wal {
+            wal.log_remove(nano)?;
+        }
+        Ok(store.remove(nano).is_some())
+    }
+
+    fn stats(&self) -> Option<StoreStats> {
+        let store = self.store_lock.read().unwrap();
+        Some(StoreStats {
+            store_size: store.total_value_size(),
+            item_count: store.item_count(),
+        })
+    }
+
+    fn health(&self) -> StoreResult<StoreHealth> {
+        let store = self.store_lock.read().unwrap();
+        Ok(StoreHealth {
+            stats: Some(StoreStats {
+                store_size: store.total_value_size(),
+                item_count: store.item_count(),
+            }),
+            max_store_size: Some(store.max_value_size()),
+            gc_running: Some(self.gc_running.load(Ordering::Relaxed)),
+        })
+    }
+}
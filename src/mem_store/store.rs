@@ -2,18 +2,38 @@ use crate::time::{sec_to_nano, NanoTime};
 
 use std::cmp::Eq;
 use std::collections::btree_map::{BTreeMap, Entry};
+use std::collections::hash_map::{self, HashMap};
 use std::hash::Hash;
+use std::mem;
 
 use linked_hash_map::LinkedHashMap;
+use sha2::{Digest as _, Sha256};
 
 pub trait WithDeadTime {
     fn dead_time(&self) -> NanoTime;
 }
 
+/// The size of a value, not counting its shared part.
 pub trait LruValueSize {
     fn lru_value_size(&self) -> usize;
 }
 
+/// A part of a value which is stored once for all equal values, keyed by the
+/// SHA-256 digest of its bytes. Cloning a part must share it.
+pub trait SharedPart {
+    type Part: Clone + AsRef<[u8]>;
+
+    fn shared_part(&self) -> Option<&Self::Part>;
+    fn set_shared_part(&mut self, part: Self::Part);
+}
+
+type Digest = [u8; 32];
+
+struct PoolEntry<P> {
+    part: P,
+    refs: usize,
+}
+
 #[derive(Debug, PartialEq, Eq)]
 pub struct StoreItem<V>
 where
@@ -22,96 +42,200 @@ where
     pub value: V,
     pub access_count: u64,
     pub size: usize,
+    dead_time: NanoTime, // the slot in queue, may differ from value.dead_time()
+    digest: Option<Digest>, // the shared part in pool
 }
 
 impl<V> StoreItem<V>
 where
-    V: LruValueSize,
+    V: LruValueSize + WithDeadTime,
 {
     #[inline]
     fn new(value: V) -> Self {
         let size = LruValueSize::lru_value_size(&value);
+        let dead_time = value.dead_time();
         Self {
             value,
             access_count: 0,
             size,
+            dead_time,
+            digest: None,
         }
     }
+
+    /// The slot in queue.
+    #[inline]
+    pub fn queue_slot(&self) -> NanoTime {
+        self.dead_time
+    }
 }
 
 pub struct Store<K, V>
 where
     K: Copy + Eq + Hash,
-    V: LruValueSize + WithDeadTime,
+    V: LruValueSize + WithDeadTime + SharedPart,
 {
     map: LinkedHashMap<K, StoreItem<V>>,
     queue: BTreeMap<NanoTime, K>,
+    pool: HashMap<Digest, PoolEntry<V::Part>>, // charged once in total_value_size
     total_value_size: usize,
     max_value_size: usize,
+    evicted: u64, // since the last take_evicted
 }
 
 impl<K, V> Store<K, V>
 where
     K: Copy + Eq + Hash,
-    V: LruValueSize + WithDeadTime,
+    V: LruValueSize + WithDeadTime + SharedPart,
 {
     pub fn new(max_value_size: usize) -> Self {
         Self {
             map: LinkedHashMap::new(),
             queue: BTreeMap::new(),
+            pool: HashMap::new(),
             total_value_size: 0,
             max_value_size,
+            evicted: 0,
         }
     }
 
     pub fn save(&mut self, key: K, value: V) {
-        let item = StoreItem::new(value);
+        self.insert(key, StoreItem::new(value));
+    }
+
+    /// Inserts an item taken from `iter`, keeping its access count and queue slot if vacant.
+    pub fn restore(&mut self, key: K, value: V, access_count: u64, queue_slot: NanoTime) {
+        let mut item = StoreItem::new(value);
+        item.access_count = access_count;
+        item.dead_time = queue_slot;
+        self.insert(key, item);
+    }
+
+    /// Points the shared part of `item` at the pool, adding it if new.
+    fn share(&mut self, item: &mut StoreItem<V>) {
+        // a removed item may still carry the digest it released
+        item.digest = None;
+        let part = match item.value.shared_part() {
+            None => return,
+            Some(part) => part.clone(),
+        };
+        let digest: Digest = Sha256::digest(part.as_ref()).into();
+        match self.pool.entry(digest) {
+            hash_map::Entry::Occupied(mut entry) => {
+                let entry = entry.get_mut();
+                entry.refs += 1;
+                item.value.set_shared_part(entry.part.clone());
+            }
+            hash_map::Entry::Vacant(entry) => {
+                self.total_value_size += part.as_ref().len();
+                entry.insert(PoolEntry { part, refs: 1 });
+            }
+        }
+        item.digest = Some(digest);
+    }
+
+    /// Drops a reference to a shared part, removing it with the last one.
+    fn release(&mut self, digest: Option<Digest>) {
+        let digest = match digest {
+            None => return,
+            Some(digest) => digest,
+        };
+        if let hash_map::Entry::Occupied(mut entry) = self.pool.entry(digest) {
+            entry.get_mut().refs -= 1;
+            if entry.get().refs == 0 {
+                self.total_value_size -= entry.remove().part.as_ref().len();
+            }
+        }
+    }
+
+    fn insert(&mut self, key: K, mut item: StoreItem<V>) {
         assert!(item.size <= self.max_value_size);
 
-        while self.max_value_size - self.total_value_size < item.size {
+        // referenced before evicting, so an evicted item can not drop it
+        self.share(&mut item);
+
+        while self.total_value_size + item.size > self.max_value_size {
             if let Some((_, it)) = self.map.pop_front() {
                 self.total_value_size -= it.size;
-                self.queue.remove(&it.value.dead_time());
+                self.queue.remove(&it.dead_time);
+                self.release(it.digest);
+                self.evicted += 1;
             } else {
                 break;
             }
         }
 
         self.total_value_size += item.size;
-        let mut dead_time = item.value.dead_time();
 
         // handle dead_time collision
         loop {
-            let entry = self.queue.entry(dead_time);
+            let entry = self.queue.entry(item.dead_time);
             if let Entry::Vacant(_) = entry {
                 entry.or_insert(key);
                 break;
             }
-            dead_time += sec_to_nano(1);
-            info!("dead_time collision: {}", dead_time);
+            item.dead_time += sec_to_nano(1);
+            info!("dead_time collision: {}", item.dead_time);
         }
 
         self.map.insert(key, item);
     }
 
+    /// Returns the number of items evicted since the last call.
+    pub fn take_evicted(&mut self) -> u64 {
+        std::mem::take(&mut self.evicted)
+    }
+
+    #[inline]
     pub fn access(&mut self, key: K) -> Option<&StoreItem<V>> {
+        self.access_many(key, 1)
+    }
+
+    /// Counts `n` accesses at once.
+    pub fn access_many(&mut self, key: K, n: u64) -> Option<&StoreItem<V>> {
         let item = self.map.get_refresh(&key)?;
-        item.access_count += 1;
+        item.access_count += n;
         Some(&(*item))
     }
 
+    /// Iterates from the least recently used.
+    #[inline]
+    pub fn iter(&self) -> impl Iterator<Item = (&K, &StoreItem<V>)> {
+        self.map.iter()
+    }
+
+    #[inline]
+    pub fn get(&self, key: K) -> Option<&StoreItem<V>> {
+        self.map.get(&key)
+    }
+
+    /// Modifies the value, keeping its access count and queue slot.
+    pub fn update(&mut self, key: K, f: impl FnOnce(&mut V)) -> Option<&StoreItem<V>> {
+        let mut item = self.remove(key)?;
+        f(&mut item.value);
+        item.size = LruValueSize::lru_value_size(&item.value);
+        self.insert(key, item);
+        self.map.get(&key)
+    }
+
+    pub fn remove(&mut self, key: K) -> Option<StoreItem<V>> {
+        let mut item = self.map.remove(&key)?;
+        self.total_value_size -= item.size;
+        self.queue.remove(&item.dead_time);
+        self.release(item.digest.take());
+        Some(item)
+    }
+
     pub fn clean(&mut self, now: NanoTime) -> usize {
         let right = self.queue.split_off(&now);
-        let count = self.queue.len();
-        let mut delta = 0;
-        for (_, key) in &self.queue {
-            if let Some(it) = self.map.remove(&key) {
-                delta += it.size;
+        let dead = mem::replace(&mut self.queue, right);
+        for key in dead.values() {
+            if let Some(it) = self.map.remove(key) {
+                self.total_value_size -= it.size;
+                self.release(it.digest);
             }
         }
-        self.queue = right;
-        self.total_value_size -= delta;
-        count
+        dead.len()
     }
 
     #[inline]
@@ -133,15 +257,35 @@ where
         self.map.len()
     }
 
+    #[inline]
+    pub fn max_value_size(&self) -> usize {
+        self.max_value_size
+    }
+
+    /// Whether `value` fits in an empty store, even if its shared part is new.
+    pub fn fits(&self, value: &V) -> bool {
+        let shared = value.shared_part().map_or(0, |part| part.as_ref().len());
+        value.lru_value_size() + shared <= self.max_value_size
+    }
+
+    /// The number of distinct shared parts.
+    #[inline]
+    pub fn shared_count(&self) -> usize {
+        self.pool.len()
+    }
+
     #[inline]
     pub fn shrink(&mut self) {
-        self.map.shrink_to_fit()
+        self.map.shrink_to_fit();
+        self.pool.shrink_to_fit();
     }
 }
 
 #[cfg(test)]
 #[test]
 fn test_store() {
+    use std::sync::Arc;
+
     #[derive(Debug, PartialEq, Eq)]
     struct Record(u128);
     impl LruValueSize for Record {
@@ -156,12 +300,24 @@ fn test_store() {
         }
     }
 
+    impl SharedPart for Record {
+        type Part = Arc<[u8]>;
+
+        fn shared_part(&self) -> Option<&Arc<[u8]>> {
+            None
+        }
+
+        fn set_shared_part(&mut self, _: Arc<[u8]>) {}
+    }
+
     let mut store = Store::new(10);
     assert_eq!(store.access(1), None);
 
     for i in 0..20 {
         store.save(i, Record(i));
     }
+    assert_eq!(store.take_evicted(), 10);
+    assert_eq!(store.take_evicted(), 0);
 
     for i in 10..20 {
         let item = store.access(i).unwrap();
@@ -180,4 +336,118 @@ fn test_store() {
             assert_eq!(v, None);
         }
     }
+
+    assert_eq!(store.get(17).unwrap().access_count, 2);
+    assert_eq!(store.access_many(16, 3).unwrap().access_count, 5);
+    let item = store.update(17, |v| v.0 = 100).unwrap();
+    assert_eq!(item.value, Record(100));
+    assert_eq!(item.access_count, 2);
+    assert_eq!(store.update(1, |v| v.0 = 100), None);
+    let item = store.remove(17).unwrap();
+    assert_eq!(item.value, Record(100));
+    assert_eq!(store.remove(17), None);
+    assert_eq!(store.access(17), None);
+    assert_eq!(store.item_count(), 4);
+    assert_eq!(store.total_value_size(), 4);
+
+    let mut restored = Store::new(10);
+    for (&k, item) in store.iter() {
+        restored.restore(
+            k,
+            Record(item.value.0),
+            item.access_count,
+            item.queue_slot(),
+        );
+    }
+    assert_eq!(
+        restored.iter().map(|(&k, _)| k).collect::<Vec<_>>(),
+        store.iter().map(|(&k, _)| k).collect::<Vec<_>>()
+    );
+    assert_eq!(restored.get(18).unwrap().access_count, 2);
+    assert_eq!(restored.total_value_size(), store.total_value_size());
+
+    store.clean(20);
+    assert_eq!(store.item_count(), 0);
+    assert_eq!(store.total_value_size(), 0);
+}
+
+#[cfg(test)]
+#[test]
+fn test_store_dedup() {
+    use std::sync::Arc;
+
+    #[derive(Debug)]
+    struct Record(u128, Option<Arc<[u8]>>);
+    impl LruValueSize for Record {
+        fn lru_value_size(&self) -> usize {
+            1
+        }
+    }
+
+    impl WithDeadTime for Record {
+        fn dead_time(&self) -> NanoTime {
+            self.0
+        }
+    }
+
+    impl SharedPart for Record {
+        type Part = Arc<[u8]>;
+
+        fn shared_part(&self) -> Option<&Arc<[u8]>> {
+            self.1.as_ref()
+        }
+
+        fn set_shared_part(&mut self, part: Arc<[u8]>) {
+            self.1 = Some(part);
+        }
+    }
+
+    let trace = |n: u8| Some(Arc::<[u8]>::from(vec![n; 4]));
+
+    let mut store = Store::new(20);
+    assert!(store.fits(&Record(0, Some(vec![0; 19].into()))));
+    assert!(!store.fits(&Record(0, Some(vec![0; 20].into()))));
+    for i in 1..=3 {
+        store.save(i, Record(i, trace(0)));
+    }
+    store.save(4, Record(4, trace(1)));
+    assert_eq!(store.shared_count(), 2);
+    assert_eq!(store.total_value_size(), 4 + 4 * 2);
+    let a = store.get(1).unwrap().value.1.as_ref().unwrap();
+    assert!(Arc::ptr_eq(
+        a,
+        store.get(3).unwrap().value.1.as_ref().unwrap()
+    ));
+
+    // expiry keeps parts still referenced
+    assert_eq!(store.clean(3), 2);
+    assert_eq!(store.shared_count(), 2);
+    assert_eq!(store.total_value_size(), 2 + 4 * 2);
+
+    assert!(store.update(3, |v| v.1 = trace(1)).is_some());
+    assert_eq!(store.shared_count(), 1);
+    assert_eq!(store.total_value_size(), 2 + 4);
+
+    // eviction drops the last reference
+    for i in 5..=8 {
+        store.save(i, Record(i, trace(i as u8)));
+    }
+    assert_eq!(
+        store.iter().map(|(&k, _)| k).collect::<Vec<_>>(),
+        vec![5, 6, 7, 8]
+    );
+    assert_eq!(store.shared_count(), 4);
+    assert_eq!(store.total_value_size(), 20);
+
+    store.clean(100);
+    assert_eq!(store.shared_count(), 0);
+    assert_eq!(store.total_value_size(), 0);
+
+    // an edit dropping the shared part releases it once
+    store.save(1, Record(200, trace(9)));
+    store.save(2, Record(201, trace(9)));
+    assert!(store.update(1, |v| v.1 = None).is_some());
+    assert!(store.remove(1).is_some());
+    assert_eq!(store.shared_count(), 1);
+    assert_eq!(store.total_value_size(), 1 + 4);
 }
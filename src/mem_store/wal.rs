@@ -0,0 +1,326 @@
+//! Write-ahead log of the memory store.
+//!
+//! Each line is a JSON `Line` with an increasing sequence number. A snapshot
+//! remembers the last sequence number it covers, so startup replays only the
+//! newer lines and compaction drops the older ones.
+
+use super::state::Store;
+use super::tmp_path;
+use crate::data::record::Record;
+use crate::time::NanoTime;
+
+use std::collections::HashMap;
+use std::fs::{self, File, OpenOptions};
+use std::io::{self, BufRead, BufReader, BufWriter, Write};
+use std::mem;
+use std::path::{Path, PathBuf};
+use std::str::FromStr;
+use std::sync::Mutex;
+
+use serde::{Deserialize, Serialize};
+
+#[derive(Serialize, Deserialize)]
+enum Op<V> {
+    Save(V),
+    Update(V),
+    Access(u64),
+    Remove,
+}
+
+#[derive(Serialize, Deserialize)]
+struct Line<V> {
+    seq: u64,
+    key: NanoTime,
+    op: Op<V>,
+}
+
+#[derive(Deserialize)]
+struct LineSeq {
+    seq: u64,
+}
+
+/// When the log is synced to disk.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Fsync {
+    /// after every write
+    Always,
+    /// in every GC round
+    Periodic,
+    /// left to the OS
+    Never,
+}
+
+impl FromStr for Fsync {
+    type Err = ();
+
+    fn from_str(s: &str) -> Result<Self, ()> {
+        match s {
+            "always" => Ok(Fsync::Always),
+            "periodic" => Ok(Fsync::Periodic),
+            "never" => Ok(Fsync::Never),
+            _ => Err(()),
+        }
+    }
+}
+
+struct Inner {
+    writer: BufWriter<File>,
+    seq: u64,
+    pending: HashMap<NanoTime, u64>, // batched access counts
+    durable_seq: u64,                // covered by the last snapshot on disk
+    compacted_seq: u64,
+}
+
+impl Inner {
+    fn write<V: Serialize>(&mut self, key: NanoTime, op: Op<V>) -> io::Result<()> {
+        self.seq += 1;
+        let line = Line {
+            seq: self.seq,
+            key,
+            op,
+        };
+        serde_json::to_writer(&mut self.writer, &line)?;
+        self.writer.write_all(b"\n")
+    }
+
+    fn write_pending(&mut self) -> io::Result<()> {
+        for (key, n) in mem::take(&mut self.pending) {
+            self.write::<()>(key, Op::Access(n))?;
+        }
+        Ok(())
+    }
+}
+
+pub struct Wal {
+    path: PathBuf,
+    fsync: Fsync,
+    inner: Mutex<Inner>,
+}
+
+fn open_append(path: &Path) -> io::Result<BufWriter<File>> {
+    let file = OpenOptions::new().create(true).append(true).open(path)?;
+    Ok(BufWriter::new(file))
+}
+
+// assert: inner.lock never returns Err or paincs
+impl Wal {
+    /// Opens the log for appending after `seq`, which must not be less than
+    /// the last sequence number in the log.
+    pub fn open(path: &Path, fsync: Fsync, seq: u64) -> io::Result<Self> {
+        let inner = Inner {
+            writer: open_append(path)?,
+            seq,
+            pending: HashMap::new(),
+            durable_seq: seq,
+            compacted_seq: seq,
+        };
+        Ok(Self {
+            path: path.to_owned(),
+            fsync,
+            inner: Mutex::new(inner),
+        })
+    }
+
+    fn append(&self, key: NanoTime, op: Op<&Record>) -> io::Result<()> {
+        let mut inner = self.inner.lock().unwrap();
+        inner.write(key, op)?;
+        if self.fsync == Fsync::Always {
+            inner.writer.flush()?;
+            inner.writer.get_ref().sync_data()?;
+        }
+        Ok(())
+    }
+
+    pub fn log_save(&self, key: NanoTime, record: &Record) -> io::Result<()> {
+        self.append(key, Op::Save(record))
+    }
+
+    pub fn log_update(&self, key: NanoTime, record: &Record) -> io::Result<()> {
+        self.append(key, Op::Update(record))
+    }
+
+    pub fn log_remove(&self, key: NanoTime) -> io::Result<()> {
+        self.append(key, Op::Remove)
+    }
+
+    /// Counts an access, written by the next `sync` or `checkpoint`.
+    pub fn log_access(&self, key: NanoTime) {
+        let mut inner = self.inner.lock().unwrap();
+        *inner.pending.entry(key).or_insert(0) += 1;
+    }
+
+    /// Writes batched access counts and syncs according to the fsync policy.
+    pub fn sync(&self) -> io::Result<()> {
+        let mut inner = self.inner.lock().unwrap();
+        inner.write_pending()?;
+        inner.writer.flush()?;
+        if self.fsync != Fsync::Never {
+            inner.writer.get_ref().sync_data()?;
+        }
+        Ok(())
+    }
+
+    /// Writes batched access counts and returns the last sequence number.
+    /// The caller must keep writers of the store out until the snapshot is taken.
+    pub fn checkpoint(&self) -> io::Result<u64> {
+        let mut inner = self.inner.lock().unwrap();
+        inner.write_pending()?;
+        inner.writer.flush()?;
+        Ok(inner.seq)
+    }
+
+    /// Marks lines up to `seq` as covered by a snapshot on disk.
+    pub fn set_durable(&self, seq: u64) {
+        let mut inner = self.inner.lock().unwrap();
+        inner.durable_seq = inner.durable_seq.max(seq);
+    }
+
+    /// Drops lines covered by a snapshot on disk.
+    /// Returns the number of kept lines, or `None` if there is nothing to drop.
+    pub fn compact(&self) -> io::Result<Option<usize>> {
+        let mut inner = self.inner.lock().unwrap();
+        if inner.durable_seq <= inner.compacted_seq {
+            return Ok(None);
+        }
+        inner.writer.flush()?;
+
+        let tmp_path = tmp_path(&self.path);
+        let mut tmp = BufWriter::new(File::create(&tmp_path)?);
+        let mut kept = 0;
+        for line in BufReader::new(File::open(&self.path)?).lines() {
+            let line = line?;
+            let seq = serde_json::from_str::<LineSeq>(&line)?.seq;
+            if seq > inner.durable_seq {
+                tmp.write_all(line.as_bytes())?;
+                tmp.write_all(b"\n")?;
+                kept += 1;
+            }
+        }
+        tmp.flush()?;
+        tmp.get_ref().sync_all()?;
+        fs::rename(&tmp_path, &self.path)?;
+
+        inner.writer = open_append(&self.path)?;
+        inner.compacted_seq = inner.durable_seq;
+        Ok(Some(kept))
+    }
+}
+
+/// Applies lines after `seq` to `store`, dropping records dead at `now`.
+/// A torn line at the end, left by a crash, is cut off. A missing file is an empty log.
+/// Returns the number of applied lines and the last sequence number.
+pub fn replay(store: &mut Store, path: &Path, seq: u64, now: NanoTime) -> io::Result<(usize, u64)> {
+    let file = match OpenOptions::new().read(true).write(true).open(path) {
+        Ok(f) => f,
+        Err(ref e) if e.kind() == io::ErrorKind::NotFound => return Ok((0, seq)),
+        Err(e) => return Err(e),
+    };
+
+    let mut reader = BufReader::new(&file);
+    let mut buf = String::new();
+    let mut valid_len = 0;
+    let mut applied = 0;
+    let mut last_seq = seq;
+    loop {
+        buf.clear();
+        let n = reader.read_line(&mut buf)?;
+        if n == 0 {
+            break;
+        }
+        let line: Line<Record> = match serde_json::from_str(&buf) {
+            Ok(line) if buf.ends_with('\n') => line,
+            _ => {
+                if reader.fill_buf()?.is_empty() {
+                    warn!("WAL: cut off a torn line at {}", valid_len);
+                    file.set_len(valid_len)?;
+                    break;
+                }
+                return Err(io::Error::new(
+                    io::ErrorKind::InvalidData,
+                    format!("bad line at {}", valid_len),
+                ));
+            }
+        };
+        valid_len += n as u64;
+        last_seq = last_seq.max(line.seq);
+        if line.seq <= seq {
+            continue;
+        }
+
+        let key = line.key;
+        match line.op {
+            Op::Save(value) => {
+                store.remove(key);
+                if value.dead_time > now {
+                    store.save(key, value);
+                }
+            }
+            Op::Update(value) => {
+                store.update(key, |v| *v = value);
+            }
+            Op::Access(n) => {
+                store.access_many(key, n);
+            }
+            Op::Remove => {
+                store.remove(key);
+            }
+        }
+        applied += 1;
+    }
+    Ok((applied, last_seq))
+}
+
+#[cfg(test)]
+#[test]
+fn test_wal() {
+    use crate::time::now_nano;
+
+    let path = std::env::temp_dir().join(format!("pastebin-wal-{}.log", now_nano()));
+    let record = |dead_time| {
+        let mut record = Record::sample();
+        record.dead_time = dead_time;
+        record
+    };
+
+    let wal = Wal::open(&path, Fsync::Always, 0).unwrap();
+    for i in 1..=3 {
+        wal.log_save(i, &record(i * 100)).unwrap();
+    }
+    wal.log_access(2);
+    wal.log_access(2);
+    let mut edited = record(200);
+    edited.title = "edited".into();
+    wal.log_update(2, &edited).unwrap();
+    wal.log_remove(3).unwrap();
+    assert_eq!(wal.checkpoint().unwrap(), 6);
+
+    let mut store = Store::new(1024 * 1024);
+    assert_eq!(replay(&mut store, &path, 0, 150).unwrap(), (6, 6));
+    assert_eq!(store.iter().map(|(&k, _)| k).collect::<Vec<_>>(), vec![2]);
+    let item = store.get(2).unwrap();
+    assert_eq!(item.value.title, "edited");
+    assert_eq!(item.access_count, 2);
+
+    // lines covered by a snapshot are skipped and compacted
+    let mut store = Store::new(1024 * 1024);
+    assert_eq!(replay(&mut store, &path, 5, 150).unwrap(), (1, 6));
+    wal.set_durable(5);
+    assert_eq!(wal.compact().unwrap(), Some(1));
+    assert_eq!(wal.compact().unwrap(), None);
+    wal.log_remove(2).unwrap();
+    drop(wal);
+
+    // a torn line is cut off
+    {
+        let mut file = OpenOptions::new().append(true).open(&path).unwrap();
+        file.write_all(b"{\"seq\":8,\"key\":1").unwrap();
+    }
+    let mut store = Store::new(1024 * 1024);
+    store.save(2, record(200));
+    assert_eq!(replay(&mut store, &path, 5, 150).unwrap(), (2, 7));
+    assert_eq!(store.item_count(), 0);
+    assert_eq!(replay(&mut store, &path, 5, 150).unwrap(), (2, 7));
+
+    fs::remove_file(&path).unwrap();
+    assert_eq!(replay(&mut store, &path, 5, 150).unwrap(), (0, 5));
+}
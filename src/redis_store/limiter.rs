@@ -0,0 +1,88 @@
+use crate::shared::rate_limit::Quota;
+
+use std::cell::RefCell;
+
+use redis::{Client, Connection, RedisResult, Script};
+
+// KEYS[1]: bucket key
+// ARGV: capacity, window_ms, now_ms, cost
+// returns the ms to wait, 0 if the tokens were taken, as Quota::take
+const TAKE_SCRIPT: &str = r"
+local capacity = tonumber(ARGV[1])
+local window = tonumber(ARGV[2])
+local now = tonumber(ARGV[3])
+local cost = tonumber(ARGV[4])
+local state = redis.call('HMGET', KEYS[1], 'tokens', 'updated')
+local tokens = tonumber(state[1]) or capacity
+local updated = tonumber(state[2]) or now
+if now > updated then
+    tokens = math.min(capacity, tokens + (now - updated) * capacity / window)
+    updated = now
+end
+local wait = 0
+if tokens >= cost then
+    tokens = tokens - cost
+else
+    wait = math.ceil((cost - tokens) * window / capacity)
+end
+redis.call('HMSET', KEYS[1], 'tokens', tostring(tokens), 'updated', updated)
+redis.call('PEXPIRE', KEYS[1], window)
+return wait
+";
+
+const KEY_PREFIX: &str = "rate_limit";
+
+lazy_static! {
+    static ref TAKE: Script = Script::new(TAKE_SCRIPT);
+}
+
+/// Buckets shared by every instance using the same Redis.
+/// Connects on first use and again after an error.
+pub struct RedisLimiter {
+    conn: RefCell<Option<Connection>>,
+    redis_url: String,
+}
+
+impl RedisLimiter {
+    pub fn new(redis_url: &str) -> Self {
+        Self {
+            conn: RefCell::new(None),
+            redis_url: redis_url.into(),
+        }
+    }
+
+    fn try_take(&self, key: &str, quota: Quota, now: u64, cost: u64) -> RedisResult<u64> {
+        let mut conn = self.conn.borrow_mut();
+        if conn.is_none() {
+            *conn = Some(Client::open(&*self.redis_url)?.get_connection()?);
+        }
+        // assert: conn is Some
+        TAKE.key(key)
+            .arg(quota.capacity)
+            .arg(quota.window_ms)
+            .arg(now)
+            .arg(cost)
+            .invoke(conn.as_ref().unwrap())
+    }
+
+    /// Lets the request through if Redis fails, rather than refusing everyone.
+    pub fn take(
+        &self,
+        budget: &str,
+        client: &str,
+        quota: Quota,
+        now: u64,
+        cost: u64,
+    ) -> Result<(), u64> {
+        let key = format!("{}:{}:{}", KEY_PREFIX, budget, client);
+        match self.try_take(&key, quota, now, cost) {
+            Ok(0) => Ok(()),
+            Ok(wait) => Err(wait),
+            Err(err) => {
+                error!("REDIS: {}", err);
+                *self.conn.borrow_mut() = None;
+                Ok(())
+            }
+        }
+    }
+}
@@ -1,16 +1,155 @@
 use super::store;
+use crate::data::key::nano_to_key;
+use crate::data::record::Record;
+use crate::shared::metrics;
+use crate::shared::store::{Access, RecordStore, StoreError, StoreHealth, StoreResult, StoreStats};
+use crate::time::NanoTime;
 
 use std::cell::RefCell;
 
+use redis::{ErrorKind, RedisError, RedisResult};
+
 pub type Store = store::RedisStore;
 
+fn parse(nano: NanoTime, json_string: &str) -> RedisResult<Record> {
+    Record::from_stored(nano, json_string)
+        .map_err(|err| RedisError::from((ErrorKind::TypeError, "Invalid record", err.to_string())))
+}
+
 pub struct State {
     pub store: RefCell<Store>,
+    redis_url: String,
 }
 
 impl State {
     pub fn new(redis_url: &str) -> Self {
         let store = RefCell::new(Store::new(redis_url).expect("Can not connect to redis"));
-        Self { store }
+        Self {
+            store,
+            redis_url: redis_url.into(),
+        }
+    }
+
+    fn try_run<T>(&self, mut f: impl FnMut(&mut Store) -> RedisResult<T>) -> StoreResult<T> {
+        let mut store = self.store.borrow_mut();
+
+        let log_error = |err| {
+            error!("REDIS: {}", err);
+        };
+        let conv_error = |err| {
+            error!("REDIS: {}", err);
+            StoreError::from(err)
+        };
+
+        // first try, an invalid value is not fixed by reconnecting
+        match f(&mut store) {
+            Ok(t) => return Ok(t),
+            Err(err) if err.kind() == ErrorKind::TypeError => return Err(conv_error(err)),
+            Err(err) => log_error(err),
+        }
+
+        let reopened = store.try_reopen(&self.redis_url);
+        metrics::REDIS_RECONNECTS
+            .with_label_values(&[if reopened.is_ok() { "ok" } else { "error" }])
+            .inc();
+        reopened.map_err(conv_error)?;
+
+        // second try
+        f(&mut store).map_err(conv_error)
+    }
+}
+
+impl RecordStore for State {
+    fn save(&self, nano: NanoTime, record: Record) -> StoreResult<()> {
+        let key = nano_to_key(nano);
+        // assert: Record is valid
+        let json_string = serde_json::to_string(&record).unwrap();
+        let max_views = record.view_limit().unwrap_or(0);
+        let protected = record.is_protected();
+        let parent_key = record.parent_key.as_deref();
+        self.try_run(|store| {
+            store.save(
+                &key,
+                &json_string,
+                record.expiration,
+                max_views,
+                protected,
+                parent_key,
+            )
+        })
+    }
+
+    fn access<G: FnOnce(&Record) -> bool>(
+        &self,
+        nano: NanoTime,
+        guard: G,
+    ) -> StoreResult<Option<Access>> {
+        let key = nano_to_key(nano);
+        let parse = |json_string: String| -> StoreResult<Record> {
+            parse(nano, &json_string).map_err(|err| {
+                error!("REDIS: {}", err);
+                StoreError::from(err)
+            })
+        };
+
+        // a protected record is returned without counting a view
+        let ans = match self.try_run(|store| store.access(&key, false))? {
+            None => None,
+            Some((0, json_string)) => {
+                if !guard(&parse(json_string)?) {
+                    return Ok(Some(Access::Denied));
+                }
+                self.try_run(|store| store.access(&key, true))?
+            }
+            ans => ans,
+        };
+
+        match ans {
+            None => Ok(None),
+            Some((access_count, json_string)) => {
+                Ok(Some(Access::Found(parse(json_string)?, access_count)))
+            }
+        }
+    }
+
+    fn peek(&self, nano: NanoTime) -> StoreResult<Option<Record>> {
+        let key = nano_to_key(nano);
+        self.try_run(|store| {
+            store
+                .peek(&key)?
+                .map(|json_string| parse(nano, &json_string))
+                .transpose()
+        })
+    }
+
+    fn update<F: FnMut(&mut Record)>(&self, nano: NanoTime, mut f: F) -> StoreResult<bool> {
+        let key = nano_to_key(nano);
+        self.try_run(|store| {
+            store.update(&key, |json_string| {
+                let mut record = parse(nano, &json_string)?;
+                f(&mut record);
+                // assert: Record is valid
+                Ok(serde_json::to_string(&record).unwrap())
+            })
+        })
+    }
+
+    fn delete(&self, nano: NanoTime) -> StoreResult<bool> {
+        let key = nano_to_key(nano);
+        self.try_run(|store| store.delete(&key))
+    }
+
+    fn stats(&self) -> Option<StoreStats> {
+        None
+    }
+
+    /// Reconnects like any other command if the PING fails.
+    fn health(&self) -> StoreResult<StoreHealth> {
+        self.try_run(|store| store.ping())?;
+        Ok(StoreHealth {
+            stats: None,
+            max_store_size: None,
+            gc_running: None,
+        })
     }
 }
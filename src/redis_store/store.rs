@@ -1,13 +1,40 @@
 use crate::time::SecTime;
 
-use redis::{Client, Commands, Connection, PipelineCommands, RedisResult};
+use redis::{Client, Commands, Connection, PipelineCommands, RedisResult, Script};
 
 pub struct RedisStore {
     conn: Connection,
 }
 
-const ACCESS_COUNT_FIELD: &'static str = "access_count";
-const VALUE_FIELD: &'static str = "value";
+const ACCESS_COUNT_FIELD: &str = "access_count";
+const VALUE_FIELD: &str = "value";
+const MAX_VIEWS_FIELD: &str = "max_views";
+const PROTECTED_FIELD: &str = "protected";
+const PARENT_KEY_FIELD: &str = "parent_key";
+
+// KEYS[1]: key
+// ARGV[1]: '1' to count a view of a protected record
+// counts a view, returns {access_count, value} and deletes the key once max_views is reached
+// returns {0, value} for a protected record unless ARGV[1] is '1'
+const ACCESS_SCRIPT: &str = r"
+if redis.call('EXISTS', KEYS[1]) == 0 then
+    return nil
+end
+if ARGV[1] ~= '1' and redis.call('HGET', KEYS[1], 'protected') == '1' then
+    return {0, redis.call('HGET', KEYS[1], 'value')}
+end
+local count = redis.call('HINCRBY', KEYS[1], 'access_count', 1)
+local value = redis.call('HGET', KEYS[1], 'value')
+local max_views = tonumber(redis.call('HGET', KEYS[1], 'max_views') or '0')
+if max_views > 0 and count >= max_views then
+    redis.call('DEL', KEYS[1])
+end
+return {count, value}
+";
+
+lazy_static! {
+    static ref ACCESS: Script = Script::new(ACCESS_SCRIPT);
+}
 
 impl RedisStore {
     pub fn new(redis_url: &str) -> RedisResult<Self> {
@@ -15,26 +42,69 @@ impl RedisStore {
         Ok(RedisStore { conn })
     }
 
-    pub fn save(&mut self, key: &str, value: &str, expiration: SecTime) -> RedisResult<()> {
-        redis::pipe()
-            .atomic()
+    /// `max_views == 0` means unlimited.
+    pub fn save(
+        &mut self,
+        key: &str,
+        value: &str,
+        expiration: SecTime,
+        max_views: u64,
+        protected: bool,
+        parent_key: Option<&str>,
+    ) -> RedisResult<()> {
+        let mut pipe = redis::pipe();
+        pipe.atomic()
             .hset(key, ACCESS_COUNT_FIELD, 0)
             .hset(key, VALUE_FIELD, value)
-            .expire(key, expiration as usize)
-            .query(&mut self.conn)
-    }
-
-    pub fn access(&mut self, key: &str) -> RedisResult<Option<(u64, String)>> {
-        let exists: bool = self.conn.exists(key)?;
-        if !exists {
-            Ok(None)
-        } else {
-            redis::pipe()
-                .hincr(key, ACCESS_COUNT_FIELD, 1)
-                .hget(key, VALUE_FIELD)
-                .query(&mut self.conn)
-                .map(|v| Some(v))
+            .hset(key, MAX_VIEWS_FIELD, max_views)
+            .hset(key, PROTECTED_FIELD, if protected { 1 } else { 0 });
+        if let Some(parent_key) = parent_key {
+            pipe.hset(key, PARENT_KEY_FIELD, parent_key);
         }
+        pipe.expire(key, expiration as usize).query(&self.conn)
+    }
+
+    pub fn access(
+        &mut self,
+        key: &str,
+        count_protected: bool,
+    ) -> RedisResult<Option<(u64, String)>> {
+        ACCESS
+            .key(key)
+            .arg(if count_protected { 1 } else { 0 })
+            .invoke(&self.conn)
+    }
+
+    pub fn peek(&mut self, key: &str) -> RedisResult<Option<String>> {
+        self.conn.hget(key, VALUE_FIELD)
+    }
+
+    pub fn update(
+        &mut self,
+        key: &str,
+        mut f: impl FnMut(String) -> RedisResult<String>,
+    ) -> RedisResult<bool> {
+        let conn = &self.conn;
+        redis::transaction(conn, &[key], |pipe| {
+            let value: Option<String> = conn.hget(key, VALUE_FIELD)?;
+            match value {
+                None => Ok(Some(false)),
+                Some(value) => pipe
+                    .hset(key, VALUE_FIELD, f(value)?)
+                    .ignore()
+                    .query::<Option<()>>(conn)
+                    .map(|ans| ans.map(|()| true)),
+            }
+        })
+    }
+
+    pub fn delete(&mut self, key: &str) -> RedisResult<bool> {
+        let count: u64 = self.conn.del(key)?;
+        Ok(count > 0)
+    }
+
+    pub fn ping(&mut self) -> RedisResult<()> {
+        redis::cmd("PING").query::<String>(&self.conn).map(drop)
     }
 
     pub fn try_reopen(&mut self, redis_url: &str) -> RedisResult<()> {
@@ -0,0 +1,93 @@
+use crate::env::ADDR;
+use crate::handler::{
+    delete_record, edit_record, find_archive, find_diff, find_file, find_html, find_raw,
+    find_record, find_revision, fork_record, healthz, metrics, readyz, save_record, upload_record,
+};
+use crate::shared::metrics::observe_request;
+use crate::shared::rate_limit::{self, Limiter, MemLimiter};
+use crate::shared::resource::route_label;
+use crate::shared::resource::{
+    json_post_config, upload_config, FIND_ARCHIVE_ROUTE, FIND_DIFF_ROUTE, FIND_FILE_ROUTE,
+    FIND_HTML_ROUTE, FIND_RAW_ROUTE, FIND_REVISION_ROUTE, FORK_RECORD_ROUTE, HEALTHZ_ROUTE,
+    METRICS_ROUTE, READYZ_ROUTE, RECORD_ROUTE, SAVE_RECORD_ROUTE, UPLOAD_RECORD_ROUTE,
+};
+use crate::shared::store::RecordStore;
+
+use std::rc::Rc;
+use std::time::Instant;
+
+use actix_web::dev::Service;
+use actix_web::{web, App, HttpServer};
+use futures::future::{self, Either};
+use futures::Future;
+
+/// `factory` is called once per worker to build its store handle.
+pub fn run_server<S, F>(factory: F, workers: Option<usize>) -> std::io::Result<()>
+where
+    S: RecordStore,
+    F: Fn() -> S + Send + Clone + 'static,
+{
+    info!("server start at {}", &*ADDR);
+
+    let mem_limiter = MemLimiter::default();
+
+    let server = HttpServer::new(move || {
+        let limiter = Rc::new(Limiter::new(&mem_limiter));
+        App::new()
+            .data(factory())
+            .wrap_fn(move |req, srv| match rate_limit::check(&limiter, &req) {
+                Some(res) => Either::A(future::ok(req.into_response(res))),
+                None => Either::B(srv.call(req)),
+            })
+            .wrap_fn(|req, srv| {
+                let route = route_label(req.path());
+                let start = Instant::now();
+                srv.call(req).then(move |res| {
+                    let status = match &res {
+                        Ok(res) => res.status(),
+                        Err(err) => err.as_response_error().render_response().status(),
+                    };
+                    observe_request(route, status.as_u16(), start.elapsed());
+                    res
+                })
+            })
+            .service(
+                web::resource(RECORD_ROUTE)
+                    .route(web::get().to(find_record::<S>))
+                    .route(web::put().to(edit_record::<S>))
+                    .route(web::delete().to(delete_record::<S>))
+                    .data(json_post_config()),
+            )
+            .service(web::resource(FIND_RAW_ROUTE).route(web::get().to(find_raw::<S>)))
+            .service(web::resource(FIND_HTML_ROUTE).route(web::get().to(find_html::<S>)))
+            .service(web::resource(FIND_FILE_ROUTE).route(web::get().to(find_file::<S>)))
+            .service(web::resource(FIND_ARCHIVE_ROUTE).route(web::get().to(find_archive::<S>)))
+            .service(
+                web::resource(FORK_RECORD_ROUTE)
+                    .route(web::post().to(fork_record::<S>))
+                    .data(json_post_config()),
+            )
+            .service(web::resource(FIND_DIFF_ROUTE).route(web::get().to(find_diff::<S>)))
+            .service(web::resource(FIND_REVISION_ROUTE).route(web::get().to(find_revision::<S>)))
+            .service(
+                web::resource(SAVE_RECORD_ROUTE)
+                    .route(web::post().to(save_record::<S>))
+                    .data(json_post_config()),
+            )
+            .service(
+                web::resource(UPLOAD_RECORD_ROUTE)
+                    .route(web::post().to(upload_record::<S>))
+                    .data(upload_config()),
+            )
+            .service(web::resource(METRICS_ROUTE).route(web::get().to(metrics)))
+            .service(web::resource(HEALTHZ_ROUTE).route(web::get().to(healthz)))
+            .service(web::resource(READYZ_ROUTE).route(web::get().to(readyz::<S>)))
+    });
+
+    let server = match workers {
+        Some(n) => server.workers(n),
+        None => server,
+    };
+
+    server.bind(&*ADDR)?.run()
+}
@@ -0,0 +1,141 @@
+use crate::data::compress::{decompress, store_body, Compressor};
+use crate::data::record::Record;
+use crate::shared::store::{Access, RecordStore, StoreHealth, StoreResult, StoreStats};
+use crate::time::NanoTime;
+
+/// Compresses records before they reach `inner` and decompresses them on the way out.
+///
+/// Must wrap `SealedStore`, since ciphertext does not compress. Without a
+/// compressor, contents are still moved into their body to be shared, and
+/// packed records still open.
+pub struct CompressedStore<S> {
+    inner: S,
+    compressor: Option<Compressor>,
+}
+
+impl<S: RecordStore> CompressedStore<S> {
+    pub fn new(inner: S, compressor: Option<Compressor>) -> Self {
+        Self { inner, compressor }
+    }
+
+    fn compress(&self, record: &mut Record) {
+        match self.compressor {
+            Some(ref compressor) => compressor.compress(record),
+            None => store_body(record),
+        }
+    }
+}
+
+impl<S: RecordStore> RecordStore for CompressedStore<S> {
+    fn save(&self, nano: NanoTime, mut record: Record) -> StoreResult<()> {
+        self.compress(&mut record);
+        self.inner.save(nano, record)
+    }
+
+    fn access<G: FnOnce(&Record) -> bool>(
+        &self,
+        nano: NanoTime,
+        guard: G,
+    ) -> StoreResult<Option<Access>> {
+        let mut guard_result = Ok(());
+        let ans = self.inner.access(nano, |record| {
+            let mut record = record.clone();
+            match decompress(&mut record) {
+                Ok(()) => guard(&record),
+                Err(err) => {
+                    guard_result = Err(err);
+                    false
+                }
+            }
+        })?;
+        guard_result?;
+
+        match ans {
+            Some(Access::Found(mut record, view_count)) => {
+                decompress(&mut record)?;
+                Ok(Some(Access::Found(record, view_count)))
+            }
+            ans => Ok(ans),
+        }
+    }
+
+    fn peek(&self, nano: NanoTime) -> StoreResult<Option<Record>> {
+        match self.inner.peek(nano)? {
+            Some(mut record) => {
+                decompress(&mut record)?;
+                Ok(Some(record))
+            }
+            None => Ok(None),
+        }
+    }
+
+    fn update<F: FnMut(&mut Record)>(&self, nano: NanoTime, mut f: F) -> StoreResult<bool> {
+        let mut unpack_result = Ok(());
+        let found = self.inner.update(nano, |record| {
+            // a record that can not be unpacked is left as is
+            let mut unpacked = record.clone();
+            unpack_result = decompress(&mut unpacked);
+            if unpack_result.is_ok() {
+                f(&mut unpacked);
+                self.compress(&mut unpacked);
+                *record = unpacked;
+            }
+        })?;
+        unpack_result?;
+        Ok(found)
+    }
+
+    fn delete(&self, nano: NanoTime) -> StoreResult<bool> {
+        self.inner.delete(nano)
+    }
+
+    fn stats(&self) -> Option<StoreStats> {
+        self.inner.stats()
+    }
+
+    fn health(&self) -> StoreResult<StoreHealth> {
+        self.inner.health()
+    }
+}
+
+#[cfg(test)]
+#[test]
+fn test_compressed_store() {
+    use crate::data::seal::Sealer;
+    use crate::mem_store::State;
+    use crate::shared::sealed_store::SealedStore;
+
+    use std::sync::Arc;
+
+    let key = base64::encode(&[1; 32]);
+    let sealer = Arc::new(Sealer::parse(&format!("a:{}", key)).unwrap());
+    let trace = "thread 'main' panicked at src/main.rs:1:1\n".repeat(20);
+    for (compressor, sealer) in &[
+        (Some(Compressor::new(3)), None),
+        (None, None),
+        (Some(Compressor::new(3)), Some(sealer.clone())),
+        (None, Some(sealer)),
+    ] {
+        let state = State::new(1024 * 1024);
+        let store =
+            CompressedStore::new(SealedStore::new(state.clone(), sealer.clone()), *compressor);
+        let mut a = Record::sample();
+        a.content = trace.clone();
+        let mut b = Record::sample();
+        b.title = "other".into();
+        b.edit("t".into(), "text".into(), trace.clone(), Vec::new(), 2);
+        store.save(1, a).unwrap();
+        store.save(2, b).unwrap();
+        store.update(2, |r| r.title = "edited".into()).unwrap();
+
+        let mem = state.store_lock.read().unwrap();
+        assert_eq!(mem.shared_count(), 1);
+        drop(mem);
+        assert_eq!(store.peek(1).unwrap().unwrap().content, trace);
+        let b = store.peek(2).unwrap().unwrap();
+        assert_eq!(
+            (b.content, b.revisions[0].content.as_str()),
+            (trace.clone(), "// content")
+        );
+    }
+}
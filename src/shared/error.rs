@@ -32,6 +32,20 @@ impl<'a> HandlerError<'a> {
         }
     }
 
+    pub fn unauthorized(err_res: ErrRes<'a>) -> Self {
+        Self {
+            status_code: StatusCode::UNAUTHORIZED,
+            err_res,
+        }
+    }
+
+    pub fn forbidden(err_res: ErrRes<'a>) -> Self {
+        Self {
+            status_code: StatusCode::FORBIDDEN,
+            err_res,
+        }
+    }
+
     pub fn not_found(err_res: ErrRes<'a>) -> Self {
         Self {
             status_code: StatusCode::NOT_FOUND,
@@ -39,6 +53,13 @@ impl<'a> HandlerError<'a> {
         }
     }
 
+    pub fn too_many_requests(err_res: ErrRes<'a>) -> Self {
+        Self {
+            status_code: StatusCode::TOO_MANY_REQUESTS,
+            err_res,
+        }
+    }
+
     pub fn internal_server_error(err_res: ErrRes<'a>) -> Self {
         Self {
             status_code: StatusCode::INTERNAL_SERVER_ERROR,
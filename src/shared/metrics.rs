@@ -0,0 +1,170 @@
+//! Prometheus metrics, exposed at `/metrics`.
+
+use std::time::Duration;
+
+use prometheus::core::Collector;
+use prometheus::{
+    Encoder, Histogram, HistogramOpts, HistogramVec, IntCounter, IntCounterVec, IntGauge, Opts,
+    Registry, TextEncoder,
+};
+
+fn register<C: Collector + Clone + 'static>(collector: C) -> C {
+    // assert: metric names are unique
+    REGISTRY.register(Box::new(collector.clone())).unwrap();
+    collector
+}
+
+// assert: metric options are valid
+lazy_static! {
+    static ref REGISTRY: Registry = Registry::new();
+    pub static ref HTTP_REQUESTS: IntCounterVec = register(
+        IntCounterVec::new(
+            Opts::new(
+                "pastebin_http_requests_total",
+                "HTTP requests by route and status"
+            ),
+            &["route", "status"]
+        )
+        .unwrap()
+    );
+    pub static ref HTTP_DURATION: HistogramVec = register(
+        HistogramVec::new(
+            HistogramOpts::new(
+                "pastebin_http_request_duration_seconds",
+                "HTTP request latency by route and status"
+            ),
+            &["route", "status"]
+        )
+        .unwrap()
+    );
+    pub static ref SAVES: IntCounter = register(
+        IntCounter::new(
+            "pastebin_saves_total",
+            "Saved records, forks and uploads included"
+        )
+        .unwrap()
+    );
+    pub static ref FINDS: IntCounter = register(
+        IntCounter::new(
+            "pastebin_finds_total",
+            "Records found and counted as viewed"
+        )
+        .unwrap()
+    );
+    pub static ref NOT_FOUND: IntCounter =
+        register(IntCounter::new("pastebin_not_found_total", "Responses with status 404").unwrap());
+    pub static ref ERRORS: IntCounter =
+        register(IntCounter::new("pastebin_errors_total", "Responses with status 5xx").unwrap());
+    pub static ref STORE_SIZE: IntGauge = register(
+        IntGauge::new(
+            "pastebin_store_size_bytes",
+            "Total value size of the memory store"
+        )
+        .unwrap()
+    );
+    pub static ref ITEM_COUNT: IntGauge =
+        register(IntGauge::new("pastebin_store_items", "Records in the memory store").unwrap());
+    pub static ref GC_PAUSE: Histogram = register(
+        Histogram::with_opts(
+            HistogramOpts::new(
+                "pastebin_gc_pause_seconds",
+                "Time the memory store is locked by a GC round"
+            )
+            .buckets(prometheus::exponential_buckets(1e-6, 4.0, 10).unwrap())
+        )
+        .unwrap()
+    );
+    pub static ref GC_REMOVED: IntCounter = register(
+        IntCounter::new("pastebin_gc_removed_total", "Expired records removed by GC").unwrap()
+    );
+    pub static ref LRU_EVICTED: IntCounter = register(
+        IntCounter::new(
+            "pastebin_lru_evicted_total",
+            "Records evicted from the full memory store"
+        )
+        .unwrap()
+    );
+    pub static ref REDIS_RECONNECTS: IntCounterVec = register(
+        IntCounterVec::new(
+            Opts::new(
+                "pastebin_redis_reconnects_total",
+                "Redis reconnections by result"
+            ),
+            &["result"]
+        )
+        .unwrap()
+    );
+}
+
+/// Registers every metric, so that all of them are rendered from the start.
+pub fn init() {
+    lazy_static::initialize(&HTTP_REQUESTS);
+    lazy_static::initialize(&HTTP_DURATION);
+    lazy_static::initialize(&SAVES);
+    lazy_static::initialize(&FINDS);
+    lazy_static::initialize(&NOT_FOUND);
+    lazy_static::initialize(&ERRORS);
+    lazy_static::initialize(&STORE_SIZE);
+    lazy_static::initialize(&ITEM_COUNT);
+    lazy_static::initialize(&GC_PAUSE);
+    lazy_static::initialize(&GC_REMOVED);
+    lazy_static::initialize(&LRU_EVICTED);
+    lazy_static::initialize(&REDIS_RECONNECTS);
+}
+
+pub fn observe_request(route: &str, status: u16, elapsed: Duration) {
+    let status_label = status.to_string();
+    HTTP_REQUESTS
+        .with_label_values(&[route, &status_label])
+        .inc();
+    HTTP_DURATION
+        .with_label_values(&[route, &status_label])
+        .observe(elapsed.as_secs_f64());
+    match status {
+        404 => NOT_FOUND.inc(),
+        500..=599 => ERRORS.inc(),
+        _ => {}
+    }
+}
+
+pub fn set_store_stats(store_size: usize, item_count: usize) {
+    STORE_SIZE.set(store_size as i64);
+    ITEM_COUNT.set(item_count as i64);
+}
+
+pub const CONTENT_TYPE: &str = "text/plain; version=0.0.4; charset=utf-8";
+
+/// Renders every metric in the Prometheus text format.
+pub fn render() -> String {
+    let mut buf = Vec::new();
+    // assert: encoding to a Vec never fails
+    TextEncoder::new()
+        .encode(&REGISTRY.gather(), &mut buf)
+        .unwrap();
+    // assert: the text format is UTF-8
+    String::from_utf8(buf).unwrap()
+}
+
+#[cfg(test)]
+#[test]
+fn test_metrics() {
+    init();
+    observe_request("/record/{key}", 404, Duration::from_millis(3));
+    observe_request("/record/{key}", 200, Duration::from_millis(1));
+    GC_PAUSE.observe(2e-6);
+    set_store_stats(100, 2);
+
+    let text = render();
+    assert!(text.contains(r#"pastebin_http_requests_total{route="/record/{key}",status="404"} 1"#));
+    assert!(text.contains(
+        r#"pastebin_http_request_duration_seconds_count{route="/record/{key}",status="404"} 1"#
+    ));
+    assert!(text.contains(
+        r#"pastebin_http_request_duration_seconds_count{route="/record/{key}",status="200"} 1"#
+    ));
+    assert!(text.contains("pastebin_not_found_total 1"));
+    assert!(text.contains("pastebin_store_size_bytes 100"));
+    assert!(text.contains("pastebin_gc_pause_seconds_count 1"));
+    // registered by init, before any increment
+    assert!(text.contains("# TYPE pastebin_lru_evicted_total counter"));
+}
@@ -1,2 +1,7 @@
+pub mod compressed_store;
 pub mod error;
+pub mod metrics;
+pub mod rate_limit;
 pub mod resource;
+pub mod sealed_store;
+pub mod store;
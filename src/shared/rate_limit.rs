@@ -0,0 +1,297 @@
+//! Per-client token buckets, checked before a request reaches its handler.
+
+use crate::data::dto::ErrRes;
+use crate::env::{
+    MAX_POST_SIZE, RATE_LIMIT_READS, RATE_LIMIT_SAVES, RATE_LIMIT_SAVE_BYTES, RATE_LIMIT_WINDOW,
+    REDIS_URL, TRUST_PROXY,
+};
+use crate::redis_store::RedisLimiter;
+use crate::shared::error::HandlerError;
+use crate::shared::resource::{
+    route_label, FIND_ARCHIVE_ROUTE, FIND_DIFF_ROUTE, FIND_FILE_ROUTE, FIND_HTML_ROUTE,
+    FIND_RAW_ROUTE, FIND_REVISION_ROUTE, FORK_RECORD_ROUTE, RECORD_ROUTE, SAVE_RECORD_ROUTE,
+    UPLOAD_RECORD_ROUTE,
+};
+use crate::time::now_nano;
+
+use std::collections::HashMap;
+use std::net::{IpAddr, SocketAddr};
+use std::sync::{Arc, Mutex};
+
+use actix_web::dev::ServiceRequest;
+use actix_web::http::{header, HeaderValue, Method};
+use actix_web::{HttpResponse, ResponseError};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum Budget {
+    Saves,
+    SaveBytes,
+    Reads,
+}
+
+impl Budget {
+    pub fn name(self) -> &'static str {
+        match self {
+            Budget::Saves => "saves",
+            Budget::SaveBytes => "save_bytes",
+            Budget::Reads => "reads",
+        }
+    }
+
+    /// Returns `None` if the budget is unlimited.
+    fn quota(self) -> Option<Quota> {
+        let capacity = match self {
+            Budget::Saves => *RATE_LIMIT_SAVES,
+            Budget::SaveBytes => *RATE_LIMIT_SAVE_BYTES,
+            Budget::Reads => *RATE_LIMIT_READS,
+        };
+        Some(Quota {
+            capacity,
+            window_ms: (*RATE_LIMIT_WINDOW).max(1) * 1000,
+        })
+        .filter(|_| capacity != 0)
+    }
+}
+
+fn is_enabled() -> bool {
+    [Budget::Saves, Budget::SaveBytes, Budget::Reads]
+        .iter()
+        .any(|b| b.quota().is_some())
+}
+
+/// `capacity` tokens, refilled continuously over `window_ms`.
+#[derive(Debug, Clone, Copy)]
+pub struct Quota {
+    pub capacity: u64,
+    pub window_ms: u64,
+}
+
+#[derive(Debug, Clone, Copy)]
+struct Bucket {
+    tokens: f64,
+    updated: u64, // ms
+    quota: Quota, // of its budget, to refill it when pruning
+}
+
+impl Quota {
+    fn refill(&self, bucket: &mut Bucket, now: u64) {
+        if now > bucket.updated {
+            let refilled = (now - bucket.updated) as f64 * self.capacity as f64;
+            bucket.tokens =
+                (bucket.tokens + refilled / self.window_ms as f64).min(self.capacity as f64);
+            bucket.updated = now;
+        }
+    }
+
+    /// Takes `cost` tokens, or returns the ms to wait for them.
+    /// The same arithmetic runs in `RedisLimiter`.
+    fn take(&self, bucket: &mut Bucket, now: u64, cost: u64) -> Result<(), u64> {
+        self.refill(bucket, now);
+        let cost = cost as f64;
+        if bucket.tokens >= cost {
+            bucket.tokens -= cost;
+            Ok(())
+        } else {
+            let wait = (cost - bucket.tokens) * self.window_ms as f64 / self.capacity as f64;
+            Err(wait.ceil() as u64)
+        }
+    }
+}
+
+#[derive(Default)]
+struct Buckets {
+    map: HashMap<(Budget, String), Bucket>,
+    next_prune: u64, // ms
+}
+
+/// Buckets shared by every worker of this process.
+#[derive(Clone, Default)]
+pub struct MemLimiter {
+    buckets: Arc<Mutex<Buckets>>,
+}
+
+// full buckets are dropped once the map grows past this, at most once per interval
+const PRUNE_LEN: usize = 4096;
+const PRUNE_INTERVAL_MS: u64 = 10_000;
+
+impl MemLimiter {
+    // assert: buckets.lock never returns Err or paincs
+    fn take(
+        &self,
+        budget: Budget,
+        client: &str,
+        quota: Quota,
+        now: u64,
+        cost: u64,
+    ) -> Result<(), u64> {
+        let mut buckets = self.buckets.lock().unwrap();
+        if buckets.map.len() >= PRUNE_LEN && now >= buckets.next_prune {
+            buckets.map.retain(|_, bucket| {
+                let quota = bucket.quota;
+                quota.refill(bucket, now);
+                bucket.tokens < quota.capacity as f64
+            });
+            buckets.next_prune = now + PRUNE_INTERVAL_MS;
+        }
+        let bucket = buckets
+            .map
+            .entry((budget, client.to_owned()))
+            .or_insert(Bucket {
+                tokens: quota.capacity as f64,
+                updated: now,
+                quota,
+            });
+        quota.take(bucket, now, cost)
+    }
+}
+
+pub enum Limiter {
+    Mem(MemLimiter),
+    Redis(RedisLimiter),
+}
+
+impl Limiter {
+    /// Shares buckets through Redis when `REDIS_URL` is set, else through `mem`.
+    pub fn new(mem: &MemLimiter) -> Self {
+        match *REDIS_URL {
+            Some(ref redis_url) => Limiter::Redis(RedisLimiter::new(redis_url)),
+            None => Limiter::Mem(mem.clone()),
+        }
+    }
+
+    /// Takes `cost` from the budget of `client`, or returns the ms to wait.
+    /// A cost above the capacity drains the bucket, so that it can be paid once full.
+    pub fn take(&self, budget: Budget, client: &str, cost: u64) -> Result<(), u64> {
+        let quota = match budget.quota() {
+            None => return Ok(()),
+            Some(quota) => quota,
+        };
+        let cost = cost.min(quota.capacity);
+        let now = (now_nano() / 1_000_000) as u64;
+        match self {
+            Limiter::Mem(mem) => mem.take(budget, client, quota, now, cost),
+            Limiter::Redis(redis) => redis.take(budget.name(), client, quota, now, cost),
+        }
+    }
+}
+
+/// The address of the client, from `Forwarded` or `X-Forwarded-For` if `TRUST_PROXY` is set.
+fn client_ip(req: &ServiceRequest) -> Option<IpAddr> {
+    if !*TRUST_PROXY {
+        return req.peer_addr().map(|addr| addr.ip());
+    }
+    let info = req.connection_info();
+    let remote = info.remote()?.trim();
+    remote
+        .parse::<SocketAddr>()
+        .map(|addr| addr.ip())
+        .or_else(|_| remote.trim_matches(|c| c == '[' || c == ']').parse())
+        .ok()
+}
+
+/// The budgets a request is charged, by route.
+fn charges(req: &ServiceRequest) -> Vec<(Budget, u64)> {
+    let route = route_label(req.path());
+    let method = req.method();
+    let saving = match route {
+        SAVE_RECORD_ROUTE | UPLOAD_RECORD_ROUTE | FORK_RECORD_ROUTE => method == Method::POST,
+        RECORD_ROUTE => method == Method::PUT,
+        _ => false,
+    };
+    if saving {
+        // bodies without a length are charged as the largest JSON body
+        let bytes = req
+            .headers()
+            .get(header::CONTENT_LENGTH)
+            .and_then(|v| v.to_str().ok())
+            .and_then(|v| v.parse().ok())
+            .unwrap_or(*MAX_POST_SIZE as u64);
+        return vec![(Budget::Saves, 1), (Budget::SaveBytes, bytes)];
+    }
+    if method != Method::GET {
+        return Vec::new();
+    }
+    match route {
+        RECORD_ROUTE | FIND_RAW_ROUTE | FIND_HTML_ROUTE | FIND_FILE_ROUTE | FIND_ARCHIVE_ROUTE
+        | FIND_REVISION_ROUTE => vec![(Budget::Reads, 1)],
+        FIND_DIFF_ROUTE => vec![(Budget::Reads, 2)],
+        _ => Vec::new(),
+    }
+}
+
+/// Returns a 429 response if the client of `req` is over one of its budgets.
+pub fn check(limiter: &Limiter, req: &ServiceRequest) -> Option<HttpResponse> {
+    if !is_enabled() {
+        return None;
+    }
+    let charges = charges(req);
+    if charges.is_empty() {
+        return None;
+    }
+    let client = format!("ip:{}", client_ip(req)?);
+    for (budget, cost) in charges {
+        if let Err(wait_ms) = limiter.take(budget, &client, cost) {
+            let retry_after = wait_ms.div_ceil(1000).max(1);
+            info!(
+                "RATE LIMITED client = {}, budget = {}, retry_after = {} s",
+                client,
+                budget.name(),
+                retry_after
+            );
+            let mut res = HandlerError::too_many_requests(ErrRes::rate_limited()).render_response();
+            res.headers_mut()
+                .insert(header::RETRY_AFTER, HeaderValue::from(retry_after));
+            return Some(res);
+        }
+    }
+    None
+}
+
+#[cfg(test)]
+#[test]
+fn test_rate_limit() {
+    let quota = Quota {
+        capacity: 3,
+        window_ms: 3000,
+    };
+    let mem = MemLimiter::default();
+    let take = |client, now, cost| mem.take(Budget::Saves, client, quota, now, cost);
+
+    assert_eq!(take("a", 0, 2), Ok(()));
+    assert_eq!(take("a", 0, 1), Ok(()));
+    assert_eq!(take("a", 0, 1), Err(1000));
+    assert_eq!(take("b", 0, 3), Ok(()));
+    assert_eq!(take("a", 500, 1), Err(500));
+    assert_eq!(take("a", 1000, 1), Ok(()));
+
+    // never above capacity
+    assert_eq!(take("a", 100_000, 3), Ok(()));
+    assert_eq!(take("a", 100_000, 1), Err(1000));
+
+    // a clock going backwards refills nothing
+    assert_eq!(take("b", 0, 1), Err(1000));
+    assert_eq!(take("b", 2500, 2), Ok(()));
+    assert_eq!(take("b", 2000, 1), Err(500));
+
+    // pruning refills each bucket with its own quota and drops the full ones
+    let bytes = Quota {
+        capacity: 1_000_000,
+        window_ms: 3000,
+    };
+    assert_eq!(take("x", 2000, 3), Ok(()));
+    for i in 3..PRUNE_LEN {
+        let client = format!("c{}", i);
+        assert_eq!(mem.take(Budget::Reads, &client, quota, 2000, 0), Ok(()));
+    }
+    assert_eq!(mem.take(Budget::SaveBytes, "x", bytes, 2100, 1), Ok(()));
+    assert_eq!(take("x", 2100, 1), Err(900));
+    assert_eq!(mem.buckets.lock().unwrap().map.len(), 4);
+
+    // not again before the interval
+    for i in 0..PRUNE_LEN {
+        let client = format!("c{}", i);
+        assert_eq!(mem.take(Budget::Reads, &client, quota, 2100, 1), Ok(()));
+    }
+    assert_eq!(mem.take(Budget::SaveBytes, "x", bytes, 2200, 1), Ok(()));
+    assert_eq!(mem.buckets.lock().unwrap().map.len(), PRUNE_LEN + 4);
+}
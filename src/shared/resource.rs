@@ -1,12 +1,50 @@
 use crate::data::dto::{ErrRes, SaveRecordReq};
-use crate::env::MAX_POST_SIZE;
+use crate::env::{MAX_POST_SIZE, MAX_UPLOAD_SIZE};
 use crate::shared::error::HandlerError;
 
+use actix_web::dev::ResourceDef;
 use actix_web::http::StatusCode;
 use actix_web::{web, FromRequest, ResponseError};
 
-pub const FIND_RECORD_ROUTE: &'static str = "/record/{key}";
-pub const SAVE_RECORD_ROUTE: &'static str = "/record";
+thread_local! {
+    // not Sync, so one per worker; more specific patterns first
+    static ROUTE_DEFS: Vec<(&'static str, ResourceDef)> = [
+        SAVE_RECORD_ROUTE,
+        UPLOAD_RECORD_ROUTE,
+        FIND_RAW_ROUTE,
+        FIND_HTML_ROUTE,
+        FIND_FILE_ROUTE,
+        FIND_ARCHIVE_ROUTE,
+        FORK_RECORD_ROUTE,
+        FIND_DIFF_ROUTE,
+        FIND_REVISION_ROUTE,
+        METRICS_ROUTE,
+        HEALTHZ_ROUTE,
+        READYZ_ROUTE,
+        RECORD_ROUTE,
+    ]
+    .iter()
+    .map(|&route| (route, ResourceDef::new(route)))
+    .collect();
+}
+
+pub const RECORD_ROUTE: &str = "/record/{key}";
+pub const SAVE_RECORD_ROUTE: &str = "/record";
+pub const UPLOAD_RECORD_ROUTE: &str = "/upload";
+pub const FIND_RAW_ROUTE: &str = "/record/{key}/raw";
+pub const FIND_HTML_ROUTE: &str = "/record/{key}/html";
+pub const FIND_FILE_ROUTE: &str = "/record/{key}/files/{name}";
+pub const FIND_ARCHIVE_ROUTE: &str = "/record/{key}/archive";
+pub const FORK_RECORD_ROUTE: &str = "/record/{key}/fork";
+pub const FIND_DIFF_ROUTE: &str = "/diff/{key_a}/{key_b}";
+pub const FIND_REVISION_ROUTE: &str = "/record/{key}/revisions/{revision}";
+pub const METRICS_ROUTE: &str = "/metrics";
+pub const HEALTHZ_ROUTE: &str = "/healthz";
+pub const READYZ_ROUTE: &str = "/readyz";
+
+pub const DELETE_TOKEN_HEADER: &str = "X-Delete-Token";
+pub const EDIT_TOKEN_HEADER: &str = "X-Edit-Token";
+pub const PASSWORD_HEADER: &str = "X-Password";
 
 pub fn json_post_config() -> <web::Json<SaveRecordReq> as FromRequest>::Config {
     web::Json::<SaveRecordReq>::configure(|cfg| {
@@ -24,3 +62,17 @@ pub fn json_post_config() -> <web::Json<SaveRecordReq> as FromRequest>::Config {
         .limit(*MAX_POST_SIZE)
     })
 }
+
+/// Leaves room for the other form fields, the file itself is checked by the handler.
+pub fn upload_config() -> web::PayloadConfig {
+    web::PayloadConfig::new(*MAX_UPLOAD_SIZE + *MAX_POST_SIZE)
+}
+
+/// Maps a request path to its route pattern, so that metric labels stay bounded.
+pub fn route_label(path: &str) -> &'static str {
+    ROUTE_DEFS.with(|defs| {
+        defs.iter()
+            .find(|(_, def)| def.is_match(path))
+            .map_or("other", |&(route, _)| route)
+    })
+}
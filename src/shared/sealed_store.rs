@@ -0,0 +1,107 @@
+use crate::data::record::Record;
+use crate::data::seal::{SealError, Sealer};
+use crate::shared::store::{Access, RecordStore, StoreHealth, StoreResult, StoreStats};
+use crate::time::NanoTime;
+
+use std::sync::Arc;
+
+/// Encrypts records before they reach `inner` and decrypts them on the way out.
+///
+/// Without a sealer, new records are stored in plaintext, but sealed records
+/// still fail to open instead of leaking ciphertext.
+pub struct SealedStore<S> {
+    inner: S,
+    sealer: Option<Arc<Sealer>>,
+}
+
+impl<S: RecordStore> SealedStore<S> {
+    pub fn new(inner: S, sealer: Option<Arc<Sealer>>) -> Self {
+        Self { inner, sealer }
+    }
+
+    fn seal(&self, nano: NanoTime, record: &mut Record) {
+        if let Some(ref sealer) = self.sealer {
+            sealer.seal(nano, record);
+        }
+    }
+
+    fn open(&self, nano: NanoTime, record: &mut Record) -> StoreResult<()> {
+        match (&self.sealer, &record.sealed_key_id) {
+            (_, None) => Ok(()),
+            (Some(sealer), Some(_)) => Ok(sealer.open(nano, record)?),
+            (None, Some(key_id)) => Err(SealError::UnknownKey(key_id.clone()).into()),
+        }
+    }
+}
+
+impl<S: RecordStore> RecordStore for SealedStore<S> {
+    fn save(&self, nano: NanoTime, mut record: Record) -> StoreResult<()> {
+        self.seal(nano, &mut record);
+        self.inner.save(nano, record)
+    }
+
+    fn access<G: FnOnce(&Record) -> bool>(
+        &self,
+        nano: NanoTime,
+        guard: G,
+    ) -> StoreResult<Option<Access>> {
+        let mut guard_result = Ok(());
+        let ans = self.inner.access(nano, |record| {
+            let mut record = record.clone();
+            match self.open(nano, &mut record) {
+                Ok(()) => guard(&record),
+                Err(err) => {
+                    guard_result = Err(err);
+                    false
+                }
+            }
+        })?;
+        guard_result?;
+
+        match ans {
+            Some(Access::Found(mut record, view_count)) => {
+                self.open(nano, &mut record)?;
+                Ok(Some(Access::Found(record, view_count)))
+            }
+            ans => Ok(ans),
+        }
+    }
+
+    fn peek(&self, nano: NanoTime) -> StoreResult<Option<Record>> {
+        match self.inner.peek(nano)? {
+            Some(mut record) => {
+                self.open(nano, &mut record)?;
+                Ok(Some(record))
+            }
+            None => Ok(None),
+        }
+    }
+
+    fn update<F: FnMut(&mut Record)>(&self, nano: NanoTime, mut f: F) -> StoreResult<bool> {
+        let mut open_result = Ok(());
+        let found = self.inner.update(nano, |record| {
+            // a record that can not be opened is left as is
+            let mut opened = record.clone();
+            open_result = self.open(nano, &mut opened);
+            if open_result.is_ok() {
+                f(&mut opened);
+                self.seal(nano, &mut opened);
+                *record = opened;
+            }
+        })?;
+        open_result?;
+        Ok(found)
+    }
+
+    fn delete(&self, nano: NanoTime) -> StoreResult<bool> {
+        self.inner.delete(nano)
+    }
+
+    fn stats(&self) -> Option<StoreStats> {
+        self.inner.stats()
+    }
+
+    fn health(&self) -> StoreResult<StoreHealth> {
+        self.inner.health()
+    }
+}
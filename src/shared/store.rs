@@ -0,0 +1,127 @@
+use crate::data::dto::ErrRes;
+use crate::data::record::Record;
+use crate::data::seal::SealError;
+use crate::shared::error::HandlerError;
+use crate::time::NanoTime;
+
+use std::fmt::{self, Display};
+use std::io;
+
+#[derive(Debug)]
+pub enum StoreError {
+    Redis(redis::RedisError),
+    Seal(SealError),
+    Io(io::Error),
+    Sqlite(rusqlite::Error),
+    TooLarge, // larger than the whole store
+}
+
+pub type StoreResult<T> = Result<T, StoreError>;
+
+impl Display for StoreError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            StoreError::Redis(err) => write!(f, "REDIS: {}", err),
+            StoreError::Seal(err) => write!(f, "SEAL: {}", err),
+            StoreError::Io(err) => write!(f, "IO: {}", err),
+            StoreError::Sqlite(err) => write!(f, "SQLITE: {}", err),
+            StoreError::TooLarge => write!(f, "record larger than the store"),
+        }
+    }
+}
+
+impl From<redis::RedisError> for StoreError {
+    fn from(err: redis::RedisError) -> Self {
+        StoreError::Redis(err)
+    }
+}
+
+impl From<SealError> for StoreError {
+    fn from(err: SealError) -> Self {
+        StoreError::Seal(err)
+    }
+}
+
+impl From<io::Error> for StoreError {
+    fn from(err: io::Error) -> Self {
+        StoreError::Io(err)
+    }
+}
+
+impl From<rusqlite::Error> for StoreError {
+    fn from(err: rusqlite::Error) -> Self {
+        StoreError::Sqlite(err)
+    }
+}
+
+impl From<StoreError> for HandlerError<'static> {
+    fn from(err: StoreError) -> Self {
+        match err {
+            StoreError::Redis(_) => HandlerError::internal_server_error(ErrRes::redis_error()),
+            StoreError::Seal(_) => HandlerError::internal_server_error(ErrRes::seal_error()),
+            StoreError::Io(_) | StoreError::Sqlite(_) => {
+                HandlerError::internal_server_error(ErrRes::storage_error())
+            }
+            StoreError::TooLarge => HandlerError::bad_request(ErrRes::too_large_file()),
+        }
+    }
+}
+
+#[allow(clippy::large_enum_variant)] // only moved out of the store once per request
+pub enum Access {
+    Found(Record, u64),
+    Denied,
+}
+
+#[derive(Debug, Clone, Copy)]
+pub struct StoreStats {
+    pub store_size: usize,
+    pub item_count: usize,
+}
+
+/// What a backend reports to `/readyz` once its check passed.
+#[derive(Debug, Clone, Copy)]
+pub struct StoreHealth {
+    pub stats: Option<StoreStats>,
+    pub max_store_size: Option<usize>,
+    /// `None` if the backend has no GC thread of its own.
+    pub gc_running: Option<bool>,
+}
+
+impl StoreHealth {
+    pub fn is_ready(&self) -> bool {
+        self.gc_running != Some(false)
+    }
+}
+
+/// A storage backend for records, keyed by the saving time.
+pub trait RecordStore: 'static {
+    fn save(&self, nano: NanoTime, record: Record) -> StoreResult<()>;
+
+    /// Returns the record and its view count after this access.
+    /// Removes the record if this access exhausts it, see `Record::is_exhausted`.
+    ///
+    /// For a protected record, `guard` is called first and the view is not counted if it fails.
+    fn access<G: FnOnce(&Record) -> bool>(
+        &self,
+        nano: NanoTime,
+        guard: G,
+    ) -> StoreResult<Option<Access>>;
+
+    /// Reads a record without counting a view.
+    fn peek(&self, nano: NanoTime) -> StoreResult<Option<Record>>;
+
+    /// Modifies a record atomically without counting a view.
+    /// `f` may be called more than once if the backend retries.
+    /// Returns `false` if the record does not exist.
+    fn update<F: FnMut(&mut Record)>(&self, nano: NanoTime, f: F) -> StoreResult<bool>;
+
+    /// Returns `false` if the record does not exist.
+    fn delete(&self, nano: NanoTime) -> StoreResult<bool>;
+
+    /// Returns `None` if the backend does not track its size locally.
+    fn stats(&self) -> Option<StoreStats>;
+
+    /// Checks that the backend can serve requests, without touching any record.
+    fn health(&self) -> StoreResult<StoreHealth>;
+}
@@ -0,0 +1,40 @@
+mod state;
+mod store;
+
+pub use self::state::State;
+
+use self::store::SqliteStore;
+use crate::env::{CLEAN_DURATION, SQLITE_PATH};
+use crate::time::now_nano;
+
+use std::thread;
+use std::time::Duration;
+
+fn start_clean(mut store: SqliteStore) {
+    thread::spawn(move || loop {
+        let now = now_nano();
+        match store.clean(now) {
+            Ok(0) => {}
+            Ok(removed_count) => info!(
+                "CLEAN removed: {}, in {} ns",
+                removed_count,
+                now_nano() - now
+            ),
+            Err(err) => error!("SQLITE: {}", err),
+        }
+
+        thread::sleep(Duration::from_millis(*CLEAN_DURATION));
+    });
+}
+
+/// Creates the schema and starts the expiry sweep. Called once.
+pub fn start() {
+    let store =
+        SqliteStore::open(SQLITE_PATH.as_ref().unwrap()).expect("Can not open sqlite database");
+    start_clean(store);
+}
+
+/// Opens a connection for a worker.
+pub fn init() -> State {
+    State::new(SQLITE_PATH.as_ref().unwrap())
+}
@@ -0,0 +1,135 @@
+use super::store::{Meta, RowAccess, SqliteStore};
+use crate::data::key::nano_to_key;
+use crate::data::record::Record;
+use crate::shared::store::{Access, RecordStore, StoreHealth, StoreResult, StoreStats};
+use crate::time::{now_nano, NanoTime};
+
+use std::cell::RefCell;
+
+use rusqlite::types::Type;
+
+pub struct State {
+    pub store: RefCell<SqliteStore>,
+}
+
+impl State {
+    pub fn new(path: &str) -> Self {
+        let store = RefCell::new(SqliteStore::open(path).expect("Can not open sqlite database"));
+        Self { store }
+    }
+}
+
+/// Fails like a `value` column that can not be read, rows may be edited by hand.
+fn parse(nano: NanoTime, json_string: &str) -> rusqlite::Result<Record> {
+    Record::from_stored(nano, json_string)
+        .map_err(|err| rusqlite::Error::FromSqlConversionFailure(0, Type::Text, Box::new(err)))
+}
+
+impl RecordStore for State {
+    fn save(&self, nano: NanoTime, record: Record) -> StoreResult<()> {
+        let key = nano_to_key(nano);
+        // assert: Record is valid
+        let json_string = serde_json::to_string(&record).unwrap();
+        let meta = Meta {
+            saving_time: record.saving_time,
+            dead_time: record.dead_time,
+            lang: &record.lang,
+            max_views: record.view_limit().unwrap_or(0),
+            protected: record.is_protected(),
+        };
+        let mut store = self.store.borrow_mut();
+        Ok(store.save(&key, &meta, &json_string)?)
+    }
+
+    fn access<G: FnOnce(&Record) -> bool>(
+        &self,
+        nano: NanoTime,
+        guard: G,
+    ) -> StoreResult<Option<Access>> {
+        let key = nano_to_key(nano);
+        let mut store = self.store.borrow_mut();
+        let ans = store.access(&key, now_nano(), |json_string| {
+            Ok(guard(&parse(nano, json_string)?))
+        })?;
+        Ok(match ans {
+            None => None,
+            Some(RowAccess::Found(json_string, access_count)) => {
+                Some(Access::Found(parse(nano, &json_string)?, access_count))
+            }
+            Some(RowAccess::Denied) => Some(Access::Denied),
+        })
+    }
+
+    fn peek(&self, nano: NanoTime) -> StoreResult<Option<Record>> {
+        let key = nano_to_key(nano);
+        let mut store = self.store.borrow_mut();
+        let ans = store.peek(&key, now_nano())?;
+        Ok(ans
+            .map(|json_string| parse(nano, &json_string))
+            .transpose()?)
+    }
+
+    fn update<F: FnMut(&mut Record)>(&self, nano: NanoTime, mut f: F) -> StoreResult<bool> {
+        let key = nano_to_key(nano);
+        let mut store = self.store.borrow_mut();
+        let ans = store.update(&key, now_nano(), |json_string| {
+            let mut record = parse(nano, &json_string)?;
+            f(&mut record);
+            // assert: Record is valid
+            Ok((record.lang.clone(), serde_json::to_string(&record).unwrap()))
+        })?;
+        Ok(ans)
+    }
+
+    fn delete(&self, nano: NanoTime) -> StoreResult<bool> {
+        let key = nano_to_key(nano);
+        let mut store = self.store.borrow_mut();
+        Ok(store.delete(&key)?)
+    }
+
+    /// Counting scans the whole table.
+    fn stats(&self) -> Option<StoreStats> {
+        None
+    }
+
+    fn health(&self) -> StoreResult<StoreHealth> {
+        self.store.borrow_mut().ping()?;
+        Ok(StoreHealth {
+            stats: None,
+            max_store_size: None,
+            gc_running: None,
+        })
+    }
+}
+
+#[cfg(test)]
+#[test]
+fn test_sqlite_state() {
+    let path = std::env::temp_dir().join(format!("pastebin-state-{}.sqlite", now_nano()));
+    let state = State::new(path.to_str().unwrap());
+    let nano = now_nano();
+    let mut record = Record::sample();
+    record.dead_time = nano + 1_000_000_000_000;
+    state.save(nano, record).unwrap();
+    assert!(state.peek(nano).unwrap().is_some());
+
+    // a row broken by hand is an error, not a panic
+    let meta = Meta {
+        saving_time: 1,
+        dead_time: nano + 1_000_000_000_000,
+        lang: "rust",
+        max_views: 0,
+        protected: false,
+    };
+    state
+        .store
+        .borrow_mut()
+        .save(&nano_to_key(nano), &meta, "{")
+        .unwrap();
+    assert!(state.peek(nano).is_err());
+    assert!(state.access(nano, |_| true).is_err());
+    assert!(state.update(nano, |_| {}).is_err());
+    assert!(state.delete(nano).unwrap());
+
+    std::fs::remove_file(&path).unwrap();
+}
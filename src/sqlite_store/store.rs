@@ -0,0 +1,252 @@
+use crate::time::NanoTime;
+
+use std::time::Duration;
+
+use rusqlite::{params, Connection, OptionalExtension, Result, TransactionBehavior};
+
+pub struct SqliteStore {
+    conn: Connection,
+}
+
+const SCHEMA: &str = "
+CREATE TABLE IF NOT EXISTS records (
+    key          TEXT PRIMARY KEY,
+    saving_time  INTEGER NOT NULL,
+    dead_time    INTEGER NOT NULL,
+    lang         TEXT NOT NULL,
+    access_count INTEGER NOT NULL DEFAULT 0,
+    max_views    INTEGER NOT NULL,
+    protected    INTEGER NOT NULL,
+    value        TEXT NOT NULL
+);
+CREATE INDEX IF NOT EXISTS records_dead_time ON records (dead_time);
+";
+
+const BUSY_TIMEOUT: Duration = Duration::from_secs(5);
+
+/// Columns of a row besides the value, for ad-hoc queries.
+pub struct Meta<'a> {
+    pub saving_time: u64,
+    pub dead_time: NanoTime,
+    pub lang: &'a str,
+    pub max_views: u64, // 0 means unlimited
+    pub protected: bool,
+}
+
+pub enum RowAccess {
+    Found(String, u64),
+    Denied,
+}
+
+// sqlite integers are i64, which holds nanoseconds until 2262
+#[inline]
+fn nano_to_i64(nano: NanoTime) -> i64 {
+    nano as i64
+}
+
+impl SqliteStore {
+    pub fn open(path: &str) -> Result<Self> {
+        let conn = Connection::open(path)?;
+        conn.busy_timeout(BUSY_TIMEOUT)?;
+        conn.pragma_update(None, "journal_mode", "WAL")?;
+        conn.execute_batch(SCHEMA)?;
+        Ok(Self { conn })
+    }
+
+    pub fn save(&mut self, key: &str, meta: &Meta, value: &str) -> Result<()> {
+        self.conn.execute(
+            "INSERT OR REPLACE INTO records
+                (key, saving_time, dead_time, lang, max_views, protected, value)
+                VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7)",
+            params![
+                key,
+                meta.saving_time as i64,
+                nano_to_i64(meta.dead_time),
+                meta.lang,
+                meta.max_views as i64,
+                meta.protected,
+                value
+            ],
+        )?;
+        Ok(())
+    }
+
+    /// Counts a view and deletes the row once max_views is reached, in one transaction.
+    /// A protected row is counted only if `guard` passes its value.
+    pub fn access(
+        &mut self,
+        key: &str,
+        now: NanoTime,
+        guard: impl FnOnce(&str) -> Result<bool>,
+    ) -> Result<Option<RowAccess>> {
+        let tx = self
+            .conn
+            .transaction_with_behavior(TransactionBehavior::Immediate)?;
+        let row: Option<(String, bool)> = tx
+            .query_row(
+                "SELECT value, protected FROM records WHERE key = ?1 AND dead_time > ?2",
+                params![key, nano_to_i64(now)],
+                |row| Ok((row.get(0)?, row.get(1)?)),
+            )
+            .optional()?;
+        let (value, protected) = match row {
+            None => return Ok(None),
+            Some(row) => row,
+        };
+        if protected && !guard(&value)? {
+            return Ok(Some(RowAccess::Denied));
+        }
+
+        let (access_count, max_views): (i64, i64) = tx.query_row(
+            "UPDATE records SET access_count = access_count + 1 WHERE key = ?1
+                RETURNING access_count, max_views",
+            params![key],
+            |row| Ok((row.get(0)?, row.get(1)?)),
+        )?;
+        if max_views > 0 && access_count >= max_views {
+            tx.execute("DELETE FROM records WHERE key = ?1", params![key])?;
+        }
+        tx.commit()?;
+
+        Ok(Some(RowAccess::Found(value, access_count as u64)))
+    }
+
+    pub fn peek(&mut self, key: &str, now: NanoTime) -> Result<Option<String>> {
+        self.conn
+            .query_row(
+                "SELECT value FROM records WHERE key = ?1 AND dead_time > ?2",
+                params![key, nano_to_i64(now)],
+                |row| row.get(0),
+            )
+            .optional()
+    }
+
+    pub fn update(
+        &mut self,
+        key: &str,
+        now: NanoTime,
+        f: impl FnOnce(String) -> Result<(String, String)>,
+    ) -> Result<bool> {
+        let tx = self
+            .conn
+            .transaction_with_behavior(TransactionBehavior::Immediate)?;
+        let value: Option<String> = tx
+            .query_row(
+                "SELECT value FROM records WHERE key = ?1 AND dead_time > ?2",
+                params![key, nano_to_i64(now)],
+                |row| row.get(0),
+            )
+            .optional()?;
+        let (lang, value) = match value {
+            None => return Ok(false),
+            Some(value) => f(value)?,
+        };
+        tx.execute(
+            "UPDATE records SET lang = ?2, value = ?3 WHERE key = ?1",
+            params![key, lang, value],
+        )?;
+        tx.commit()?;
+        Ok(true)
+    }
+
+    pub fn delete(&mut self, key: &str) -> Result<bool> {
+        let count = self
+            .conn
+            .execute("DELETE FROM records WHERE key = ?1", params![key])?;
+        Ok(count > 0)
+    }
+
+    /// Deletes rows dead at `now`. Returns the number of deleted rows.
+    pub fn clean(&mut self, now: NanoTime) -> Result<usize> {
+        self.conn.execute(
+            "DELETE FROM records WHERE dead_time <= ?1",
+            params![nano_to_i64(now)],
+        )
+    }
+
+    /// Reads at most one row, so that it costs the same on any table size.
+    pub fn ping(&mut self) -> Result<()> {
+        self.conn
+            .query_row("SELECT 1 FROM records LIMIT 1", params![], |_| Ok(()))
+            .optional()
+            .map(drop)
+    }
+}
+
+#[cfg(test)]
+#[test]
+fn test_sqlite_store() {
+    use crate::time::now_nano;
+
+    let path = std::env::temp_dir().join(format!("pastebin-{}.sqlite", now_nano()));
+    let mut store = SqliteStore::open(path.to_str().unwrap()).unwrap();
+    store.ping().unwrap();
+    let stats = |store: &SqliteStore| -> (usize, usize) {
+        store
+            .conn
+            .query_row(
+                "SELECT TOTAL(LENGTH(value)), COUNT(*) FROM records",
+                params![],
+                |row| {
+                    Ok((
+                        row.get::<_, f64>(0)? as usize,
+                        row.get::<_, i64>(1)? as usize,
+                    ))
+                },
+            )
+            .unwrap()
+    };
+    let meta = |dead_time, max_views, protected| Meta {
+        saving_time: 1,
+        dead_time,
+        lang: "rust",
+        max_views,
+        protected,
+    };
+
+    store.save("a", &meta(100, 2, false), "va").unwrap();
+    store.save("b", &meta(200, 0, true), "vb").unwrap();
+    store.save("c", &meta(300, 0, false), "vc").unwrap();
+    assert_eq!(stats(&store), (6, 3));
+    store.ping().unwrap();
+
+    let mut access =
+        |key, now, guard: fn(&str) -> Result<bool>| match store.access(key, now, guard).unwrap() {
+            None => None,
+            Some(RowAccess::Found(value, count)) => Some(Some((value, count))),
+            Some(RowAccess::Denied) => Some(None),
+        };
+    assert_eq!(access("a", 50, |_| Ok(false)), Some(Some(("va".into(), 1))));
+    assert_eq!(access("b", 50, |_| Ok(false)), Some(None));
+    assert_eq!(
+        access("b", 50, |v| Ok(v == "vb")),
+        Some(Some(("vb".into(), 1)))
+    );
+
+    // exhausted
+    assert_eq!(access("a", 50, |_| Ok(true)), Some(Some(("va".into(), 2))));
+    assert_eq!(access("a", 50, |_| Ok(true)), None);
+    assert_eq!(access("c", 350, |_| Ok(true)), None);
+
+    // expired but not swept
+    assert_eq!(store.peek("b", 250).unwrap(), None);
+    assert_eq!(store.peek("b", 150).unwrap().unwrap(), "vb");
+
+    assert!(store
+        .update("c", 150, |v| Ok(("text".into(), v + "2")))
+        .unwrap());
+    assert!(!store.update("x", 150, |v| Ok((String::new(), v))).unwrap());
+    assert_eq!(store.peek("c", 150).unwrap().unwrap(), "vc2");
+    assert!(matches!(
+        store.access("c", 150, |_| Ok(true)).unwrap(),
+        Some(RowAccess::Found(_, 1))
+    ));
+
+    assert_eq!(store.clean(250).unwrap(), 1);
+    assert!(store.delete("c").unwrap());
+    assert!(!store.delete("c").unwrap());
+    assert_eq!(stats(&store), (0, 0));
+
+    drop(store);
+    std::fs::remove_file(&path).unwrap();
+}